@@ -0,0 +1,377 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{de::DeserializeOwned, Serialize};
+use tracing::{debug, warn};
+
+use crate::error::{GcpError, Result};
+use crate::github::{GitHubFile, GitTree, RepositoryInfo};
+use crate::github::auth::TokenValidation;
+use crate::downloader::CopyState;
+
+/// On-disk cache for GitHub metadata (listings, repository info) and file blobs
+///
+/// Layout under the cache root:
+///   metadata/<host>/<owner>/<repo>/repo.json
+///   metadata/<host>/<owner>/<repo>/<ref>/<path>.json   (contents listings)
+///   metadata/<host>/<owner>/<repo>/<ref>/trees/<path>.json (recursive git trees, with file modes)
+///   metadata/<host>/<owner>/<repo>/<ref>/shallow/<path>.json (a directory's own tree entries)
+///   blobs/<sha[..2]>/<sha[2..]>                 (raw file content keyed by git blob SHA, shared by all hosts)
+///   auth/<token sha256>.json                    (token validation results)
+///   state/<destination sha256>.json             (what folder copies wrote, for incremental re-copies)
+#[derive(Debug, Clone)]
+pub struct Cache {
+    root: PathBuf,
+    /// Host whose repositories the metadata belongs to
+    host: String,
+}
+
+impl Cache {
+    /// Open a cache rooted at the given directory, creating it if needed
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root).map_err(|e| GcpError::FileIo {
+            path: root.clone(),
+            source: e,
+        })?;
+        Ok(Self {
+            root,
+            host: crate::github::DEFAULT_HOST.to_string(),
+        })
+    }
+
+    /// Keep metadata for repositories on `host`, e.g. a GitHub Enterprise Server
+    ///
+    /// The same `owner/repo` on another host is a different repository.
+    pub fn for_host(self, host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            ..self
+        }
+    }
+
+    /// Open the cache configured in `CacheConfig`, or the default cache directory
+    pub fn from_config(config: &crate::CacheConfig) -> Result<Self> {
+        match &config.dir {
+            Some(dir) => Self::new(dir),
+            None => Self::new(crate::get_cache_dir()?),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get a cached contents listing for `path` at `ref_`
+    pub fn get_listing(&self, owner: &str, repo: &str, ref_: &str, path: &str) -> Result<Option<Vec<GitHubFile>>> {
+        self.read_json(&self.listing_path(owner, repo, ref_, path))
+    }
+
+    /// Store a contents listing for `path` at `ref_`
+    pub fn put_listing(&self, owner: &str, repo: &str, ref_: &str, path: &str, items: &[GitHubFile]) -> Result<()> {
+        self.write_json(&self.listing_path(owner, repo, ref_, path), &items)
+    }
+
+    /// Find the metadata entry for a single file, either from its own listing
+    /// or from the listing of its parent directory
+    pub fn get_file_entry(&self, owner: &str, repo: &str, ref_: &str, path: &str) -> Result<Option<GitHubFile>> {
+        if let Some(items) = self.get_listing(owner, repo, ref_, path)? {
+            if let Some(entry) = items.into_iter().find(|item| item.path == path && !item.is_directory()) {
+                return Ok(Some(entry));
+            }
+        }
+
+        let parent = path.rsplit_once('/').map(|(parent, _)| parent).unwrap_or("");
+        if let Some(items) = self.get_listing(owner, repo, ref_, parent)? {
+            return Ok(items.into_iter().find(|item| item.path == path));
+        }
+
+        Ok(None)
+    }
+
+    /// Get a cached tree of `path` at `ref_`, recursive or just the directory's own entries
+    ///
+    /// A recursive tree also answers for the directory's own entries.
+    pub fn get_tree(&self, owner: &str, repo: &str, ref_: &str, path: &str, recursive: bool) -> Result<Option<GitTree>> {
+        if !recursive {
+            if let Some(tree) = self.read_json(&self.tree_path(owner, repo, ref_, path, false))? {
+                return Ok(Some(tree));
+            }
+        }
+        self.read_json(&self.tree_path(owner, repo, ref_, path, true))
+    }
+
+    /// Store a tree of `path` at `ref_`, recursive or just the directory's own entries
+    pub fn put_tree(&self, owner: &str, repo: &str, ref_: &str, path: &str, recursive: bool, tree: &GitTree) -> Result<()> {
+        self.write_json(&self.tree_path(owner, repo, ref_, path, recursive), tree)
+    }
+
+    pub fn get_repository_info(&self, owner: &str, repo: &str) -> Result<Option<RepositoryInfo>> {
+        self.read_json(&self.repo_dir(owner, repo).join("repo.json"))
+    }
+
+    pub fn put_repository_info(&self, owner: &str, repo: &str, info: &RepositoryInfo) -> Result<()> {
+        self.write_json(&self.repo_dir(owner, repo).join("repo.json"), info)
+    }
+
+    /// Get the cached validation result for a token, keyed by its hash
+    pub fn get_token_validation(&self, token_hash: &str) -> Result<Option<TokenValidation>> {
+        self.read_json(&self.root.join("auth").join(format!("{}.json", encode_component(token_hash))))
+    }
+
+    pub fn put_token_validation(&self, token_hash: &str, validation: &TokenValidation) -> Result<()> {
+        self.write_json(&self.root.join("auth").join(format!("{}.json", encode_component(token_hash))), validation)
+    }
+
+    /// Get the state left by previous folder copies into `destination`
+    pub fn get_copy_state(&self, destination: &Path) -> Result<Option<CopyState>> {
+        self.read_json(&self.state_path(destination))
+    }
+
+    pub fn put_copy_state(&self, destination: &Path, state: &CopyState) -> Result<()> {
+        self.write_json(&self.state_path(destination), state)
+    }
+
+    /// Get cached blob content by git blob SHA
+    pub fn get_blob(&self, sha: &str) -> Result<Option<Vec<u8>>> {
+        let path = match self.blob_path(sha) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        match fs::read(&path) {
+            Ok(content) => {
+                debug!("Blob cache hit: {}", sha);
+                Ok(Some(content))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(GcpError::FileIo { path, source: e }),
+        }
+    }
+
+    /// Whether content for the blob SHA is cached, without reading it
+    pub fn has_blob(&self, sha: &str) -> bool {
+        self.blob_path(sha).is_some_and(|path| path.is_file())
+    }
+
+    /// Store blob content under its git blob SHA
+    pub fn put_blob(&self, sha: &str, content: &[u8]) -> Result<()> {
+        match self.blob_path(sha) {
+            Some(path) => self.write_atomic(&path, content),
+            None => {
+                warn!("Refusing to cache blob with malformed SHA: {}", sha);
+                Ok(())
+            }
+        }
+    }
+
+    fn repo_dir(&self, owner: &str, repo: &str) -> PathBuf {
+        self.root
+            .join("metadata")
+            .join(encode_component(&self.host))
+            .join(encode_component(owner))
+            .join(encode_component(repo))
+    }
+
+    fn listing_path(&self, owner: &str, repo: &str, ref_: &str, path: &str) -> PathBuf {
+        // Prefix with '/' so the repository root gets a non-empty file name
+        let key = format!("/{}", path.trim_matches('/'));
+        self.repo_dir(owner, repo)
+            .join(encode_component(ref_))
+            .join(format!("{}.json", encode_component(&key)))
+    }
+
+    fn tree_path(&self, owner: &str, repo: &str, ref_: &str, path: &str, recursive: bool) -> PathBuf {
+        // Listing file names always start with an encoded '/', so `trees` and `shallow` can't collide
+        let key = format!("/{}", path.trim_matches('/'));
+        self.repo_dir(owner, repo)
+            .join(encode_component(ref_))
+            .join(if recursive { "trees" } else { "shallow" })
+            .join(format!("{}.json", encode_component(&key)))
+    }
+
+    /// State files are keyed by the destination's absolute path
+    fn state_path(&self, destination: &Path) -> PathBuf {
+        use sha2::{Digest, Sha256};
+
+        let absolute = destination.canonicalize()
+            .or_else(|_| std::env::current_dir().map(|dir| dir.join(destination)))
+            .unwrap_or_else(|_| destination.to_path_buf());
+        let key = format!("{:x}", Sha256::digest(absolute.to_string_lossy().as_bytes()));
+        self.root.join("state").join(format!("{}.json", key))
+    }
+
+    fn blob_path(&self, sha: &str) -> Option<PathBuf> {
+        if sha.len() < 4 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let sha = sha.to_ascii_lowercase();
+        Some(self.root.join("blobs").join(&sha[..2]).join(&sha[2..]))
+    }
+
+    fn read_json<T: DeserializeOwned>(&self, path: &Path) -> Result<Option<T>> {
+        let content = match fs::read(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(GcpError::FileIo { path: path.to_path_buf(), source: e }),
+        };
+
+        match serde_json::from_slice(&content) {
+            Ok(value) => {
+                debug!("Metadata cache hit: {}", path.display());
+                Ok(Some(value))
+            }
+            Err(e) => {
+                // A corrupt entry is treated as a miss rather than a hard failure
+                warn!("Ignoring unreadable cache entry {}: {}", path.display(), e);
+                Ok(None)
+            }
+        }
+    }
+
+    fn write_json<T: Serialize + ?Sized>(&self, path: &Path, value: &T) -> Result<()> {
+        let content = serde_json::to_vec(value)?;
+        self.write_atomic(path, &content)
+    }
+
+    fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+        let parent = path.parent().unwrap_or(&self.root);
+        fs::create_dir_all(parent).map_err(|e| GcpError::FileIo {
+            path: parent.to_path_buf(),
+            source: e,
+        })?;
+
+        let mut temp = tempfile::NamedTempFile::new_in(parent).map_err(|e| GcpError::FileIo {
+            path: parent.to_path_buf(),
+            source: e,
+        })?;
+        temp.write_all(content).map_err(|e| GcpError::FileIo {
+            path: temp.path().to_path_buf(),
+            source: e,
+        })?;
+        temp.persist(path).map_err(|e| GcpError::FileIo {
+            path: path.to_path_buf(),
+            source: e.error,
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Encode a string so it is safe to use as a single path component
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '%' | '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => {
+                encoded.push_str(&format!("%{:02X}", c as u32));
+            }
+            _ => encoded.push(c),
+        }
+    }
+    if encoded == "." || encoded == ".." {
+        encoded = encoded.replace('.', "%2E");
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn entry(path: &str, sha: &str, file_type: &str) -> GitHubFile {
+        GitHubFile {
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            path: path.to_string(),
+            sha: sha.to_string(),
+            size: 0,
+            url: String::new(),
+            html_url: String::new(),
+            git_url: String::new(),
+            download_url: None,
+            file_type: file_type.to_string(),
+            content: None,
+            encoding: None,
+        }
+    }
+
+    #[test]
+    fn test_listing_roundtrip_and_file_lookup() {
+        let dir = tempdir().unwrap();
+        let cache = Cache::new(dir.path()).unwrap();
+
+        let items = vec![entry("src/lib.rs", "aaaa1111", "file"), entry("src/bin", "bbbb2222", "dir")];
+        cache.put_listing("owner", "repo", "feature/x", "src", &items).unwrap();
+
+        let listed = cache.get_listing("owner", "repo", "feature/x", "src").unwrap().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert!(cache.get_listing("owner", "repo", "main", "src").unwrap().is_none());
+
+        let file = cache.get_file_entry("owner", "repo", "feature/x", "src/lib.rs").unwrap().unwrap();
+        assert_eq!(file.sha, "aaaa1111");
+        assert!(cache.get_file_entry("owner", "repo", "feature/x", "src/missing.rs").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_metadata_is_per_host() {
+        let dir = tempdir().unwrap();
+        let github = Cache::new(dir.path()).unwrap();
+        let enterprise = Cache::new(dir.path()).unwrap().for_host("ghe.example.com");
+
+        github.put_listing("owner", "repo", "main", "src", &[entry("src/lib.rs", "aaaa1111", "file")]).unwrap();
+        assert!(enterprise.get_listing("owner", "repo", "main", "src").unwrap().is_none());
+        assert!(enterprise.get_file_entry("owner", "repo", "main", "src/lib.rs").unwrap().is_none());
+
+        // Blobs are content-addressed, so every host shares them
+        let sha = crate::filesystem::git_blob_sha(b"hello\n");
+        github.put_blob(&sha, b"hello\n").unwrap();
+        assert!(enterprise.has_blob(&sha));
+    }
+
+    #[test]
+    fn test_tree_roundtrip_and_modes() {
+        let dir = tempdir().unwrap();
+        let cache = Cache::new(dir.path()).unwrap();
+
+        let tree: GitTree = serde_json::from_str(r#"{"sha": "t", "tree": [
+            {"path": "bin/run.sh", "mode": "100755", "type": "blob", "sha": "1"},
+            {"path": "bin/README", "mode": "100644", "type": "blob", "sha": "2"}
+        ]}"#).unwrap();
+        cache.put_tree("owner", "repo", "main", "bin", true, &tree).unwrap();
+        // A listing of the same path is stored separately
+        cache.put_listing("owner", "repo", "main", "bin", &[]).unwrap();
+
+        let cached = cache.get_tree("owner", "repo", "main", "bin", true).unwrap().unwrap();
+        assert_eq!(cached.mode("bin/run.sh"), Some(0o100755));
+        assert_eq!(cached.mode("bin/README"), Some(0o100644));
+        assert_eq!(cached.mode("bin/missing"), None);
+        assert!(cache.get_tree("owner", "repo", "main", "", true).unwrap().is_none());
+
+        // A directory's own entries are stored apart, and fall back to the recursive tree
+        assert!(cache.get_tree("owner", "repo", "main", "bin", false).unwrap().is_some());
+        let shallow: GitTree = serde_json::from_str(r#"{"sha": "t", "tree": [
+            {"path": "run.sh", "mode": "100644", "type": "blob", "sha": "1"}
+        ]}"#).unwrap();
+        cache.put_tree("owner", "repo", "main", "", false, &shallow).unwrap();
+        assert_eq!(cache.get_tree("owner", "repo", "main", "", false).unwrap().unwrap().mode("run.sh"), Some(0o100644));
+        assert!(cache.get_tree("owner", "repo", "main", "", true).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_blob_roundtrip() {
+        let dir = tempdir().unwrap();
+        let cache = Cache::new(dir.path()).unwrap();
+
+        let sha = crate::filesystem::git_blob_sha(b"hello\n");
+        assert!(cache.get_blob(&sha).unwrap().is_none());
+        assert!(!cache.has_blob(&sha));
+        cache.put_blob(&sha, b"hello\n").unwrap();
+        assert!(cache.has_blob(&sha));
+        assert_eq!(cache.get_blob(&sha).unwrap().unwrap(), b"hello\n");
+
+        // Malformed SHAs never map to a path
+        cache.put_blob("../../etc", b"x").unwrap();
+        assert!(cache.get_blob("../../etc").unwrap().is_none());
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::Serialize;
+
+use crate::filesystem::PathFilter;
+use crate::github::GitHubFile;
+use super::folder::relative_path;
+
+/// How one path differs between a remote folder and a local directory
+///
+/// Statuses describe what the remote side would change locally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    /// Only in the remote folder
+    Added,
+    /// Only in the local directory
+    Removed,
+    /// In both, with different content
+    Modified,
+}
+
+impl DiffStatus {
+    /// One-letter code, as in `git diff --name-status`
+    pub fn code(self) -> char {
+        match self {
+            DiffStatus::Added => 'A',
+            DiffStatus::Removed => 'D',
+            DiffStatus::Modified => 'M',
+        }
+    }
+}
+
+/// A path that differs, relative to the compared folders
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffEntry {
+    pub path: String,
+    pub status: DiffStatus,
+    /// Path in the repository, when the file exists remotely
+    pub repo_path: Option<String>,
+    pub remote_sha: Option<String>,
+    pub local_sha: Option<String>,
+}
+
+/// Compare a recursive remote listing of `root` with local blob SHAs
+///
+/// Only regular remote files take part; local files shadowing a remote symlink or
+/// submodule are left alone. Paths outside the `--include`/`--exclude` selection
+/// are ignored on both sides.
+pub fn compare(root: &str, remote: &[GitHubFile], local: &BTreeMap<String, String>, filter: &PathFilter) -> Vec<DiffEntry> {
+    let root = root.trim_matches('/');
+    let mut ignored = BTreeSet::new();
+    let mut entries = Vec::new();
+
+    for file in remote.iter().filter(|file| !file.is_directory()) {
+        let path = relative_path(root, &file.path);
+        if !file.is_file() {
+            ignored.insert(path);
+            continue;
+        }
+        if !is_selected(filter, path) {
+            continue;
+        }
+
+        let local_sha = local.get(path);
+        let status = match local_sha {
+            None => DiffStatus::Added,
+            Some(sha) if *sha != file.sha => DiffStatus::Modified,
+            Some(_) => {
+                ignored.insert(path);
+                continue;
+            }
+        };
+        ignored.insert(path);
+        entries.push(DiffEntry {
+            path: path.to_string(),
+            status,
+            repo_path: Some(file.path.clone()),
+            remote_sha: Some(file.sha.clone()),
+            local_sha: local_sha.cloned(),
+        });
+    }
+
+    for (path, sha) in local {
+        if !ignored.contains(path.as_str()) && is_selected(filter, path) {
+            entries.push(DiffEntry {
+                path: path.clone(),
+                status: DiffStatus::Removed,
+                repo_path: None,
+                remote_sha: None,
+                local_sha: Some(sha.clone()),
+            });
+        }
+    }
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries
+}
+
+/// Whether a relative file path passes the filter, including excluded parent directories
+pub(crate) fn is_selected(filter: &PathFilter, path: &str) -> bool {
+    let excluded_dir = path.match_indices('/').any(|(index, _)| filter.is_excluded(&path[..index]));
+    !excluded_dir && !filter.is_excluded(path) && filter.is_included(path)
+}
+
+/// A unified diff from the local to the remote content of `path`
+pub fn unified_diff(path: &str, local: &[u8], remote: &[u8]) -> String {
+    match (std::str::from_utf8(local), std::str::from_utf8(remote)) {
+        (Ok(local), Ok(remote)) => similar::TextDiff::from_lines(local, remote)
+            .unified_diff()
+            .header(&format!("a/{}", path), &format!("b/{}", path))
+            .to_string(),
+        _ => format!("Binary files a/{} and b/{} differ\n", path, path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, sha: &str, file_type: &str) -> GitHubFile {
+        GitHubFile {
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            path: path.to_string(),
+            sha: sha.to_string(),
+            size: 0,
+            url: String::new(),
+            html_url: String::new(),
+            git_url: String::new(),
+            download_url: None,
+            file_type: file_type.to_string(),
+            content: None,
+            encoding: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_reports_changes() {
+        let remote = vec![
+            file("docs/same.md", "1", "file"),
+            file("docs/changed.md", "2", "file"),
+            file("docs/new.md", "3", "file"),
+            file("docs/sub", "4", "dir"),
+            file("docs/link", "5", "symlink"),
+            file("docs/tests/a.rs", "6", "file"),
+        ];
+        let local: BTreeMap<String, String> = [("same.md", "1"), ("changed.md", "x"), ("old.md", "7"), ("link", "8"), ("tests/b.rs", "9")]
+            .into_iter()
+            .map(|(path, sha)| (path.to_string(), sha.to_string()))
+            .collect();
+        let filter = PathFilter::new(&[], &["tests".to_string()]).unwrap();
+
+        let diff = compare("docs", &remote, &local, &filter);
+        let summary: Vec<(&str, DiffStatus)> = diff.iter().map(|entry| (entry.path.as_str(), entry.status)).collect();
+        assert_eq!(summary, [
+            ("changed.md", DiffStatus::Modified),
+            ("new.md", DiffStatus::Added),
+            ("old.md", DiffStatus::Removed),
+        ]);
+        assert_eq!(diff[0].repo_path.as_deref(), Some("docs/changed.md"));
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let patch = unified_diff("a.txt", b"one\ntwo\n", b"one\nthree\n");
+        assert!(patch.starts_with("--- a/a.txt\n+++ b/a.txt\n"));
+        assert!(patch.contains("-two\n+three\n"));
+        assert_eq!(unified_diff("x.bin", &[0xff], b""), "Binary files a/x.bin and b/x.bin differ\n");
+    }
+}
@@ -1,11 +1,16 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tracing::{debug, info, error};
+use std::time::SystemTime;
+use tracing::{debug, error, warn};
 
 use crate::error::{GcpError, Result};
-use crate::github::{GitHubClient, GitHubUrl};
-use crate::filesystem::{create_intermediate_dirs, resolve_conflict, ensure_destination_dir};
-use crate::downloader::ProgressReporter;
+use crate::TimestampSource;
+use crate::github::{client::HISTORY_BATCH, GitHubClient, GitHubUrl, GitTree, UrlType};
+use crate::filesystem::{
+    create_intermediate_dirs, resolve_conflict, ensure_destination_dir, git_blob_sha, write_with_metadata, ConflictResolution, FileMetadata,
+};
+use crate::downloader::{DownloadPlan, DownloadedFile, PlannedAction, PlannedFile, ProgressReporter, SkipReason};
 
 /// Downloads individual files from GitHub repositories
 pub struct FileDownloader {
@@ -28,6 +33,35 @@ impl FileDownloader {
 
     /// Download a single file from GitHub
     pub async fn download_file(&self, github_url: &GitHubUrl, destination: &PathBuf, force: bool) -> Result<PathBuf> {
+        self.download_file_with_sha(github_url, None, destination, force)
+            .await
+            .map(|file| file.local_path)
+    }
+
+    /// Download a single file whose blob SHA may already be known (e.g. from a folder listing)
+    ///
+    /// A known SHA lets the content be served from the blob cache without a request.
+    /// The file mode comes from the git tree of the file's directory. Returns what
+    /// was written, including how an existing file was dealt with.
+    pub async fn download_file_with_sha(&self, github_url: &GitHubUrl, sha: Option<&str>, destination: &PathBuf, force: bool) -> Result<DownloadedFile> {
+        let path = github_url.path.as_deref().unwrap_or("");
+        let tree = self.git_tree(github_url).await;
+        let metadata = FileMetadata {
+            modified: self.preserved_times(github_url, &[path.to_string()]).await?.remove(path),
+            ..self.file_metadata(tree.as_ref(), path)
+        };
+        self.download_file_with_metadata(github_url, sha, destination, force, &metadata).await
+    }
+
+    /// Download a single file and give it `metadata`'s permissions and modification time
+    pub async fn download_file_with_metadata(
+        &self,
+        github_url: &GitHubUrl,
+        sha: Option<&str>,
+        destination: &PathBuf,
+        force: bool,
+        metadata: &FileMetadata,
+    ) -> Result<DownloadedFile> {
         debug!("Downloading file from {} to {:?}", github_url.raw_url().unwrap_or_default(), destination);
 
         // Ensure the file type is correct
@@ -41,21 +75,204 @@ impl FileDownloader {
         // Ensure destination directory exists
         ensure_destination_dir(destination)?;
 
-        // Handle file conflicts
-        let final_destination = if force && destination.exists() {
-            // Force overwrite
-            destination.clone()
+        // Handle file conflicts: overwrite with force, otherwise auto-rename
+        let action = PlannedAction::for_destination(destination, force);
+        let final_destination = action.target_path(destination);
+        let conflict = action.conflict_resolution().unwrap_or(ConflictResolution::NoConflict);
+        create_intermediate_dirs(&final_destination)?;
+
+        let content = self.fetch_content(github_url, sha).await?;
+        let size = content.len() as u64;
+        let limit = self.github_client.config().download.max_file_size;
+        if size > limit {
+            return Err(GcpError::FileTooLarge { size, limit });
+        }
+        let sha = git_blob_sha(&content);
+
+        // Write content to file
+        write_with_metadata(&final_destination, &content, metadata)?;
+
+        Ok(DownloadedFile {
+            repo_path: github_url.path.clone().unwrap_or_default(),
+            local_path: final_destination,
+            size,
+            sha,
+            conflict,
+        })
+    }
+
+    /// Decide what downloading the file to `destination` would do, without writing anything
+    ///
+    /// Looks up the file's size and blob SHA with one Contents API request (or the
+    /// metadata cache when offline).
+    pub async fn plan_file(&self, github_url: &GitHubUrl, destination: &Path, force: bool) -> Result<DownloadPlan> {
+        let path = github_url.path.as_deref().unwrap_or("");
+        let items = self.github_client
+            .list_contents(&github_url.owner, &github_url.repo, path, github_url.ref_.as_deref())
+            .await?;
+        let item = items.into_iter()
+            .find(|item| item.path == path && !item.is_directory())
+            .ok_or_else(|| GcpError::InvalidOperation {
+                operation: "download_file".to_string(),
+                reason: format!("{} is not a file", path),
+            })?;
+
+        let size = item.size.max(0) as u64;
+        let action = if size > self.github_client.config().download.max_file_size {
+            PlannedAction::Skip { reason: SkipReason::TooLarge }
         } else {
-            // Auto-rename to avoid conflicts
-            resolve_conflict(destination)
+            PlannedAction::for_destination(destination, force)
         };
-        create_intermediate_dirs(&final_destination)?;
+        let cached = self.github_client.cache().is_some_and(|cache| cache.has_blob(&item.sha));
+        let writes = usize::from(!matches!(action, PlannedAction::Skip { .. }));
+
+        Ok(DownloadPlan {
+            files: vec![PlannedFile {
+                repo_path: item.path,
+                local_path: destination.to_path_buf(),
+                size,
+                sha: item.sha,
+                action,
+                cached,
+                symlink: None,
+                copied_from: None,
+            }],
+            listing_requests: if self.github_client.is_offline() { 0 } else { 1 },
+            symlink_requests: 0,
+            metadata_requests: self.metadata_requests(writes, self.github_client.config().filesystem.preserve_timestamps),
+            deletions: Vec::new(),
+        })
+    }
+
+    /// Requests for modes and modification times when writing `files` files
+    /// with timestamps from `timestamps`, for `--dry-run`
+    ///
+    /// History is looked up with GraphQL when there is a token, and with a
+    /// request per file otherwise.
+    pub fn metadata_requests(&self, files: usize, timestamps: Option<TimestampSource>) -> usize {
+        if files == 0 || self.github_client.is_offline() {
+            return 0;
+        }
+        let times = match timestamps {
+            None => 0,
+            Some(TimestampSource::Commit) => 1,
+            Some(TimestampSource::History) if self.github_client.is_authenticated() => files.div_ceil(HISTORY_BATCH),
+            Some(TimestampSource::History) => files,
+        };
+        usize::from(self.needs_git_modes()) + times
+    }
+
+    /// Whether file modes depend on the git tree
+    ///
+    /// With `default_permissions` of `0755`, executable and other files are
+    /// written alike, so the tree isn't fetched.
+    fn needs_git_modes(&self) -> bool {
+        self.github_client.config().filesystem.default_permissions != Some(0o755)
+    }
+
+    /// The git tree at the URL's ref covering what it points at, for file modes
+    ///
+    /// That is the recursive tree of a folder URL's path, or just the entries of
+    /// a file URL's directory. `None` when it isn't needed or can't be fetched;
+    /// files then get the default permissions.
+    pub async fn git_tree(&self, github_url: &GitHubUrl) -> Option<GitTree> {
+        if !self.needs_git_modes() {
+            return None;
+        }
+        let path = github_url.path.as_deref().unwrap_or("");
+        let path = match github_url.url_type {
+            UrlType::File => path.rsplit_once('/').map_or("", |(parent, _)| parent),
+            _ => path,
+        };
+        let recursive = github_url.url_type != UrlType::File;
+        match self.github_client.get_tree(&github_url.owner, &github_url.repo, path, github_url.ref_.as_deref(), recursive).await {
+            Ok(tree) => Some(tree),
+            // Offline copies of listings don't come with trees
+            Err(e @ GcpError::NotCached { .. }) => {
+                debug!("Using default file modes: {}", e);
+                None
+            }
+            Err(e) => {
+                warn!("Using default file modes: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Permissions for the file at `repo_path`, from its mode in `tree`
+    pub fn file_metadata(&self, tree: Option<&GitTree>, repo_path: &str) -> FileMetadata {
+        let git_mode = tree.and_then(|tree| tree.mode(repo_path));
+        FileMetadata::from_git_mode(git_mode, self.github_client.config().filesystem.default_permissions)
+    }
+
+    /// Modification times for `paths` at the URL's ref, taken from `source`
+    ///
+    /// Paths the source has no date for are left out.
+    pub async fn file_times(&self, github_url: &GitHubUrl, paths: &[String], source: TimestampSource) -> Result<HashMap<String, SystemTime>> {
+        let (owner, repo, ref_) = (&github_url.owner, &github_url.repo, github_url.ref_.as_deref());
+        match source {
+            TimestampSource::Commit => {
+                let commit = self.github_client.resolve_commit(owner, repo, ref_).await?;
+                let Some(date) = commit.committer_date.or(commit.date) else {
+                    return Ok(HashMap::new());
+                };
+                Ok(paths.iter().map(|path| (path.clone(), date.into())).collect())
+            }
+            TimestampSource::History => {
+                let dates = self.github_client.last_commit_dates(owner, repo, paths, ref_).await?;
+                Ok(dates.into_iter().map(|(path, date)| (path, date.into())).collect())
+            }
+        }
+    }
+
+    /// Modification times to give `paths`, per `FilesystemConfig::preserve_timestamps`
+    ///
+    /// Empty when timestamps aren't preserved, or when the history can't be
+    /// fetched; files then keep the time they are written. Rate limits and
+    /// rejected credentials are errors, as they would fail the download anyway.
+    pub async fn preserved_times(&self, github_url: &GitHubUrl, paths: &[String]) -> Result<HashMap<String, SystemTime>> {
+        let Some(source) = self.github_client.config().filesystem.preserve_timestamps else {
+            return Ok(HashMap::new());
+        };
+        match self.file_times(github_url, paths, source).await {
+            Ok(times) => Ok(times),
+            Err(e) if e.is_access_error() => Err(e),
+            Err(e) => {
+                warn!("Not preserving timestamps: {}", e);
+                Ok(HashMap::new())
+            }
+        }
+    }
 
+    /// Fetch the content of a file without writing it anywhere
+    ///
+    /// Order of preference: blob cache (when the SHA is known), raw URL, GitHub API.
+    /// In offline mode only the cache is consulted.
+    pub async fn fetch_content(&self, github_url: &GitHubUrl, sha: Option<&str>) -> Result<Vec<u8>> {
+        let path = github_url.path.as_deref().unwrap_or("");
+        let ref_ = github_url.ref_.as_deref();
+
+        if self.github_client.is_offline() {
+            return self.github_client.get_cached_file(&github_url.owner, &github_url.repo, path, ref_, sha);
+        }
+
+        if let (Some(sha), Some(cache)) = (sha, self.github_client.cache()) {
+            if let Some(content) = cache.get_blob(sha)? {
+                return Ok(content);
+            }
+        }
+
+        let content = self.fetch_remote_content(github_url).await?;
+        self.github_client.cache_file(&github_url.owner, &github_url.repo, path, ref_, &content);
+        Ok(content)
+    }
+
+    async fn fetch_remote_content(&self, github_url: &GitHubUrl) -> Result<Vec<u8>> {
         // Try to use raw URL first (easier, no auth required for public repos)
         if let Some(raw_url) = github_url.raw_url() {
             debug!("Attempting download from raw URL: {}", raw_url);
-            match self.download_from_raw_url(&raw_url, &final_destination).await {
-                Ok(path) => return Ok(path),
+            match self.github_client.download_file_content(&raw_url).await {
+                Ok(content) => return Ok(content),
                 Err(e) => {
                     debug!("Raw URL download failed, falling back to GitHub API: {}", e);
                 }
@@ -64,7 +281,7 @@ impl FileDownloader {
 
         // Fallback to GitHub API
         debug!("Using GitHub API for file download");
-        let (content, size) = self.github_client
+        let (content, _size) = self.github_client
             .get_file_info(
                 &github_url.owner,
                 &github_url.repo,
@@ -73,19 +290,11 @@ impl FileDownloader {
             )
             .await?;
 
-        // Write content to file
-        tokio::fs::write(&final_destination, content).await
-            .map_err(|e| GcpError::FileIo {
-                path: final_destination.clone(),
-                source: e,
-            })?;
-
-  
-        Ok(final_destination)
+        Ok(content.into_bytes())
     }
 
     /// Download file from raw URL (fallback method)
-    pub async fn download_from_raw_url(&self, raw_url: &str, destination: &PathBuf) -> Result<PathBuf> {
+    pub async fn download_from_raw_url(&self, raw_url: &str, destination: &Path) -> Result<PathBuf> {
         debug!("Downloading from raw URL: {}", raw_url);
 
         // Ensure destination directory exists
@@ -1,17 +1,27 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::SystemTime;
 use tracing::{debug, info, warn, error};
 
 use crate::error::{GcpError, Result};
+use crate::TimestampSource;
 use crate::github::{GitHubClient, GitHubUrl, GitHubFile};
-use crate::filesystem::{create_intermediate_dirs, ensure_destination_dir};
-use crate::downloader::{FileDownloader, ProgressReporter};
+use crate::filesystem::{
+    ConflictResolution, create_intermediate_dirs, create_symlink, ensure_destination_dir, git_blob_sha, is_within, local_blob_shas, local_paths, matches_blob_sha,
+    resolve_link_target, FileMetadata, OutputSink, PathFilter,
+};
+use crate::downloader::{
+    diff, CopyState, DiffEntry, DownloadEvent, DownloadedFile, DownloadPlan, DownloadReport, EventSink, FailedFile, FileDownloader, PlannedAction, PlannedFile,
+    ProgressReporter, SkipReason,
+};
 
 /// Downloads entire folders from GitHub repositories
 pub struct FolderDownloader {
     github_client: Arc<GitHubClient>,
     file_downloader: Arc<FileDownloader>,
     progress: Option<Arc<ProgressReporter>>,
+    events: Option<EventSink>,
 }
 
 impl FolderDownloader {
@@ -21,6 +31,7 @@ impl FolderDownloader {
             github_client,
             file_downloader,
             progress: None,
+            events: None,
         }
     }
 
@@ -29,9 +40,48 @@ impl FolderDownloader {
         self
     }
 
+    /// Report each file to `events` as soon as it is downloaded or fails
+    pub fn with_events(mut self, events: EventSink) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Download an entire folder from GitHub recursively, returning the number of files written
+    pub async fn download_folder(&self, github_url: &GitHubUrl, destination: &Path, force: bool) -> Result<usize> {
+        self.download_folder_report(github_url, destination, force)
+            .await
+            .map(|report| report.files.len())
+    }
+
     /// Download an entire folder from GitHub recursively
-    pub async fn download_folder(&self, github_url: &GitHubUrl, destination: &PathBuf, force: bool) -> Result<usize> {
-        debug!("Downloading folder from {} to {:?}", github_url.api_path(), destination);
+    ///
+    /// A file that fails to download doesn't stop the rest; it is recorded in the
+    /// report's `errors` instead.
+    pub async fn download_folder_report(&self, github_url: &GitHubUrl, destination: &Path, force: bool) -> Result<DownloadReport> {
+        // Mirroring replaces whatever differs instead of writing next to it
+        let force = force || self.github_client.config().download.delete;
+        let plan = self.plan_folder(github_url, destination, force).await?;
+
+        // Ensure destination directory exists
+        ensure_destination_dir(destination)?;
+        create_intermediate_dirs(destination)?;
+
+        let mut report = self.download_plan(github_url, &plan, force).await?;
+        self.remove_escaping_links(destination, &mut report);
+        self.delete_extraneous(destination, &plan.deletions, &mut report);
+        self.save_copy_state(github_url, destination, &report);
+        info!("Successfully downloaded {} files to {}", report.files.len(), destination.display());
+        Ok(report)
+    }
+
+    /// List the folder recursively and decide what would happen to each file
+    ///
+    /// Applies the `--include`/`--exclude` filters, the size limit and the conflict
+    /// policy against the current destination, without writing anything. With
+    /// `DownloadConfig::delete`, unchanged files are skipped and local files missing
+    /// from the source are planned for deletion.
+    pub async fn plan_folder(&self, github_url: &GitHubUrl, destination: &Path, force: bool) -> Result<DownloadPlan> {
+        debug!("Planning folder download from {} to {:?}", github_url.api_path(), destination);
 
         // Ensure the URL type is correct
         if github_url.url_type != crate::github::UrlType::Folder {
@@ -41,90 +91,565 @@ impl FolderDownloader {
             });
         }
 
-        // Ensure destination directory exists
-        ensure_destination_dir(destination)?;
-        create_intermediate_dirs(destination)?;
+        let config = &self.github_client.config().download;
+        let filter = PathFilter::new(&config.include, &config.exclude)?;
+        let root = github_url.path.as_deref().unwrap_or("").trim_matches('/');
+
+        let force = force || config.delete;
+        let state = self.copy_state(destination);
+        let mut plan = DownloadPlan::default();
+        let scope = PlanScope { root, force, filter: &filter, state: &state, archive: false };
+        self.plan_recursive(github_url, destination, &scope, &mut plan).await?;
+        plan.metadata_requests += self.file_downloader.metadata_requests(plan.writes().count(), self.github_client.config().filesystem.preserve_timestamps);
+        if config.delete {
+            plan.deletions = plan_deletions(root, destination, &filter, &plan)?;
+        }
+        Ok(plan)
+    }
+
+    /// List the folder recursively and decide which files go into an archive
+    ///
+    /// Applies the `--include`/`--exclude` filters and the size limit. Each file's
+    /// `local_path` is its path inside the archive, relative to the folder.
+    pub async fn plan_archive(&self, github_url: &GitHubUrl) -> Result<DownloadPlan> {
+        if github_url.url_type != crate::github::UrlType::Folder {
+            let name = github_url.path.as_deref().unwrap_or("").rsplit('/').next().unwrap_or("");
+            let mut plan = self.file_downloader.plan_file(github_url, Path::new(name), true).await?;
+            for file in plan.files.iter_mut().filter(|file| file.is_write()) {
+                file.action = PlannedAction::Create;
+            }
+            plan.metadata_requests = self.file_downloader.metadata_requests(plan.writes().count(), self.archive_timestamps());
+            return Ok(plan);
+        }
 
-        // Start folder download
-        let mut downloaded_files = 0;
-        self.download_folder_recursive(github_url, destination, &mut downloaded_files, force).await?;
+        let config = &self.github_client.config().download;
+        let filter = PathFilter::new(&config.include, &config.exclude)?;
+        let root = github_url.path.as_deref().unwrap_or("").trim_matches('/');
 
-        info!("Successfully downloaded {} files to {}", downloaded_files, destination.display());
-        Ok(downloaded_files)
+        let state = CopyState::default();
+        let mut plan = DownloadPlan::default();
+        let scope = PlanScope { root, force: true, filter: &filter, state: &state, archive: true };
+        self.plan_recursive(github_url, Path::new(""), &scope, &mut plan).await?;
+        plan.metadata_requests += self.file_downloader.metadata_requests(plan.writes().count(), self.archive_timestamps());
+        Ok(plan)
     }
 
-    /// Recursively download folder contents
-    fn download_folder_recursive<'a>(
+    /// Recursively plan folder contents
+    fn plan_recursive<'a>(
         &'a self,
         github_url: &'a GitHubUrl,
-        destination: &'a PathBuf,
-        downloaded_files: &'a mut usize,
-        force: bool,
+        destination: &'a Path,
+        scope: &'a PlanScope<'a>,
+        plan: &'a mut DownloadPlan,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
         Box::pin(async move {
             debug!("Processing folder: {}", github_url.api_path());
 
             // Get folder contents from GitHub API
             let contents = self.get_folder_contents(github_url).await?;
+            if !self.github_client.is_offline() {
+                plan.listing_requests += 1;
+            }
 
             for item in contents {
                 let item_destination = destination.join(&item.name);
+                let relative = relative_path(scope.root, &item.path);
 
-                if item.is_file() {
-                    // Download file
-                    debug!("Downloading file: {}", item.path);
-
-                    let file_url = GitHubUrl {
-                        owner: github_url.owner.clone(),
-                        repo: github_url.repo.clone(),
-                        path: Some(item.path.clone()),
-                        ref_: github_url.ref_.clone(),
-                        url_type: crate::github::UrlType::File,
-                    };
-
-                    match self.file_downloader.download_file(&file_url, &item_destination, force).await {
-                        Ok(_) => {
-                            *downloaded_files += 1;
-                            if let Some(ref progress) = self.progress {
-                                progress.set_message(&format!("Downloaded {} files", downloaded_files));
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to download file {}: {}", item.path, e);
-                            // Continue with other files even if one fails
-                        }
+                if item.is_directory() {
+                    // An excluded directory is not listed at all
+                    if scope.filter.is_excluded(relative) {
+                        debug!("Excluding directory: {}", item.path);
+                        continue;
                     }
-                } else if item.is_directory() {
-                    // Recursively download subfolder
-                    debug!("Entering subdirectory: {}", item.path);
 
+                    debug!("Entering subdirectory: {}", item.path);
                     let folder_url = GitHubUrl {
+                        host: github_url.host.clone(),
                         owner: github_url.owner.clone(),
                         repo: github_url.repo.clone(),
                         path: Some(item.path.clone()),
                         ref_: github_url.ref_.clone(),
                         url_type: crate::github::UrlType::Folder,
                     };
+                    self.plan_recursive(&folder_url, &item_destination, scope, plan).await?;
+                    continue;
+                }
 
-                    // Create subdirectory
-                    create_intermediate_dirs(&item_destination)?;
+                let selected = !scope.filter.is_excluded(relative) && scope.filter.is_included(relative);
+                if item.is_symlink() && selected {
+                    self.plan_symlink(github_url, item, item_destination, scope, plan).await;
+                    continue;
+                }
 
-                    self.download_folder_recursive(&folder_url, &item_destination, downloaded_files, force).await?;
-                } else if item.is_submodule() {
-                    debug!("Skipping submodule: {}", item.path);
+                let size = item.size.max(0) as u64;
+                let action = if item.is_submodule() {
                     // TODO: Handle submodules if needed
-                } else if item.is_symlink() {
-                    debug!("Skipping symlink: {}", item.path);
-                    // TODO: Handle symlinks if needed
-                }
+                    PlannedAction::Skip { reason: SkipReason::Submodule }
+                } else if scope.filter.is_excluded(relative) {
+                    PlannedAction::Skip { reason: SkipReason::Excluded }
+                } else if !scope.filter.is_included(relative) {
+                    PlannedAction::Skip { reason: SkipReason::NotIncluded }
+                } else {
+                    self.write_action(scope, relative, &item.sha, size, &item_destination)
+                };
+
+                let cached = self.github_client.cache().is_some_and(|cache| cache.has_blob(&item.sha));
+                plan.files.push(PlannedFile {
+                    repo_path: item.path,
+                    local_path: item_destination,
+                    size,
+                    sha: item.sha,
+                    action,
+                    cached,
+                    symlink: None,
+                    copied_from: None,
+                });
             }
 
             Ok(())
         })
     }
 
+    /// What to do with a selected file of `size` bytes with blob `sha`
+    fn write_action(&self, scope: &PlanScope<'_>, relative: &str, sha: &str, size: u64, destination: &Path) -> PlannedAction {
+        let config = &self.github_client.config().download;
+        if size > config.max_file_size {
+            PlannedAction::Skip { reason: SkipReason::TooLarge }
+        } else if scope.archive {
+            PlannedAction::Create
+        } else if scope.state.is_unchanged(relative, sha, destination)
+            // Mirroring also recognises identical files this copy didn't write
+            || (config.delete && matches_blob_sha(destination, sha))
+        {
+            PlannedAction::Skip { reason: SkipReason::Unchanged }
+        } else {
+            PlannedAction::for_destination(destination, scope.force)
+        }
+    }
+
+    /// Plan a symlink that passed the `--include`/`--exclude` filters
+    ///
+    /// The target is read from the link's blob. A link is recreated only when its
+    /// target stays inside the destination. With `DownloadConfig::copy_symlinks`,
+    /// or where symlinks can't be created, the file it points to is copied instead.
+    async fn plan_symlink(&self, github_url: &GitHubUrl, item: GitHubFile, destination: std::path::PathBuf, scope: &PlanScope<'_>, plan: &mut DownloadPlan) {
+        let relative = relative_path(scope.root, &item.path).to_string();
+        let mut planned = PlannedFile {
+            repo_path: item.path.clone(),
+            local_path: destination,
+            size: item.size.max(0) as u64,
+            sha: item.sha.clone(),
+            action: PlannedAction::Skip { reason: SkipReason::Symlink },
+            cached: self.github_client.cache().is_some_and(|cache| cache.has_blob(&item.sha)),
+            symlink: None,
+            copied_from: None,
+        };
+
+        let target = match self.github_client.get_blob(&github_url.owner, &github_url.repo, &item.sha).await {
+            Ok(content) => {
+                if !planned.cached {
+                    plan.symlink_requests += 1;
+                }
+                String::from_utf8_lossy(&content).into_owned()
+            }
+            Err(e) => {
+                warn!("Skipping symlink {}: {}", item.path, e);
+                plan.files.push(planned);
+                return;
+            }
+        };
+
+        // Archives can hold symlinks whatever the platform
+        let copy = self.github_client.config().download.copy_symlinks || (!cfg!(unix) && !scope.archive);
+        if !copy {
+            planned.action = if resolve_link_target(&relative, &target).is_none() {
+                warn!("Skipping symlink {} -> {}: it points outside the destination", item.path, target);
+                PlannedAction::Skip { reason: SkipReason::UnsafeSymlink }
+            } else if scope.archive {
+                PlannedAction::Create
+            } else if std::fs::read_link(&planned.local_path).is_ok_and(|existing| existing == Path::new(&target)) {
+                PlannedAction::Skip { reason: SkipReason::Unchanged }
+            } else {
+                PlannedAction::for_destination(&planned.local_path, scope.force)
+            };
+            // The target is already in hand, so writing the link needs no request
+            planned.cached = true;
+            planned.size = target.len() as u64;
+            planned.symlink = Some(target);
+            plan.files.push(planned);
+            return;
+        }
+
+        // A copy may come from anywhere in the repository, since only content is written
+        let Some(target_path) = resolve_link_target(&item.path, &target) else {
+            warn!("Skipping symlink {} -> {}: it points outside the repository", item.path, target);
+            planned.action = PlannedAction::Skip { reason: SkipReason::UnsafeSymlink };
+            plan.files.push(planned);
+            return;
+        };
+        let listing = self.github_client.list_contents(&github_url.owner, &github_url.repo, &target_path, github_url.ref_.as_deref()).await;
+        if !self.github_client.is_offline() {
+            plan.listing_requests += 1;
+        }
+        let target_file = match listing {
+            Ok(items) => items.into_iter().find(|entry| entry.path == target_path && entry.is_file()),
+            Err(e) => {
+                debug!("Failed to look up {}: {}", target_path, e);
+                None
+            }
+        };
+
+        match target_file {
+            Some(file) => {
+                planned.size = file.size.max(0) as u64;
+                planned.action = self.write_action(scope, &relative, &file.sha, planned.size, &planned.local_path);
+                planned.cached = self.github_client.cache().is_some_and(|cache| cache.has_blob(&file.sha));
+                planned.sha = file.sha;
+                planned.copied_from = Some(target_path);
+            }
+            None => warn!("Skipping symlink {} -> {}: the target is not a file", item.path, target),
+        }
+        plan.files.push(planned);
+    }
+
+    /// Download the files a plan writes
+    ///
+    /// A file that fails to download doesn't stop the rest; it is recorded in the
+    /// report's `errors` instead. Fails only when the rate limit or credentials
+    /// stop the timestamps from being looked up.
+    pub async fn download_plan(&self, github_url: &GitHubUrl, plan: &DownloadPlan, force: bool) -> Result<DownloadReport> {
+        let mut report = DownloadReport::default();
+        // Modes and timestamps for the whole folder take a few requests, made only when something is written
+        let repo_paths: Vec<String> = plan.writes().map(|file| file.repo_path.clone()).collect();
+        let (tree, times) = if repo_paths.is_empty() {
+            (None, HashMap::new())
+        } else {
+            let tree = self.file_downloader.git_tree(github_url).await;
+            (tree, self.file_downloader.preserved_times(github_url, &repo_paths).await?)
+        };
+
+        for item in &plan.files {
+            if !item.is_write() {
+                debug!("Skipping {}: {:?}", item.repo_path, item.action);
+                continue;
+            }
+
+            // Download file
+            debug!("Downloading file: {}", item.repo_path);
+
+            let file_url = GitHubUrl {
+                host: github_url.host.clone(),
+                owner: github_url.owner.clone(),
+                repo: github_url.repo.clone(),
+                path: Some(item.content_path().to_string()),
+                ref_: github_url.ref_.clone(),
+                url_type: crate::github::UrlType::File,
+            };
+
+            let metadata = FileMetadata {
+                modified: times.get(&item.repo_path).copied(),
+                ..self.file_downloader.file_metadata(tree.as_ref(), item.content_path())
+            };
+            let result = match &item.symlink {
+                Some(target) => write_symlink(item, target),
+                None => self.file_downloader
+                    .download_file_with_metadata(&file_url, Some(&item.sha), &item.local_path, force, &metadata)
+                    .await
+                    // A copied symlink is reported, and remembered, under its own path
+                    .map(|file| DownloadedFile { repo_path: item.repo_path.clone(), ..file }),
+            };
+            match result {
+                Ok(file) => {
+                    if let Some(ref events) = self.events {
+                        events(DownloadEvent::Downloaded(&file));
+                    }
+                    report.files.push(file);
+                    if let Some(ref progress) = self.progress {
+                        progress.set_message(&format!("Downloaded {} files", report.files.len()));
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to download file {}: {}", item.repo_path, e);
+                    // Continue with other files even if one fails
+                    let failed = FailedFile::new(&item.repo_path, &e);
+                    if let Some(ref events) = self.events {
+                        events(DownloadEvent::Failed(&failed));
+                    }
+                    report.errors.push(failed);
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Stream the files a plan writes into `sink` instead of the destination directory
+    ///
+    /// Files get executable bits from the git tree, and timestamps as with
+    /// `--preserve-timestamps`, defaulting to the resolved commit's date. A file
+    /// that fails to download doesn't stop the rest, so every failure is
+    /// reported, but the sink is then left unfinished: an archive missing files
+    /// is never put in place.
+    pub async fn archive_plan(&self, github_url: &GitHubUrl, plan: &DownloadPlan, sink: &mut dyn OutputSink) -> Result<DownloadReport> {
+        let tree = self.file_downloader.git_tree(github_url).await;
+        let times = self.archive_times(github_url, plan).await?;
+        let max_file_size = self.github_client.config().download.max_file_size;
+        let mut report = DownloadReport::default();
+
+        for item in plan.writes() {
+            debug!("Archiving file: {}", item.repo_path);
+            let file_url = GitHubUrl {
+                host: github_url.host.clone(),
+                owner: github_url.owner.clone(),
+                repo: github_url.repo.clone(),
+                path: Some(item.content_path().to_string()),
+                ref_: github_url.ref_.clone(),
+                url_type: crate::github::UrlType::File,
+            };
+
+            let written = async {
+                let metadata = FileMetadata {
+                    modified: times.get(&item.repo_path).copied(),
+                    ..self.file_downloader.file_metadata(tree.as_ref(), item.content_path())
+                };
+                if let Some(target) = &item.symlink {
+                    sink.write_symlink(&item.local_path, target, &metadata)?;
+                    return Ok(DownloadedFile {
+                        repo_path: item.repo_path.clone(),
+                        local_path: item.local_path.clone(),
+                        size: target.len() as u64,
+                        sha: item.sha.clone(),
+                        conflict: ConflictResolution::NoConflict,
+                    });
+                }
+
+                let content = self.file_downloader.fetch_content(&file_url, Some(&item.sha)).await?;
+                let size = content.len() as u64;
+                if size > max_file_size {
+                    return Err(GcpError::FileTooLarge { size, limit: max_file_size });
+                }
+                sink.write_file(&item.local_path, &content, &metadata)?;
+                Ok(DownloadedFile {
+                    repo_path: item.repo_path.clone(),
+                    local_path: item.local_path.clone(),
+                    size,
+                    sha: git_blob_sha(&content),
+                    conflict: ConflictResolution::NoConflict,
+                })
+            }.await;
+
+            match written {
+                Ok(file) => {
+                    if let Some(ref events) = self.events {
+                        events(DownloadEvent::Downloaded(&file));
+                    }
+                    report.files.push(file);
+                    if let Some(ref progress) = self.progress {
+                        progress.set_message(&format!("Archived {} files", report.files.len()));
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to archive file {}: {}", item.repo_path, e);
+                    let failed = FailedFile::new(&item.repo_path, &e);
+                    if let Some(ref events) = self.events {
+                        events(DownloadEvent::Failed(&failed));
+                    }
+                    report.errors.push(failed);
+                }
+            }
+        }
+
+        if !report.is_partial() {
+            sink.finish()?;
+        }
+        Ok(report)
+    }
+
+    /// Where archive entries get their timestamps: as with `--preserve-timestamps`,
+    /// defaulting to the resolved commit
+    fn archive_timestamps(&self) -> Option<TimestampSource> {
+        Some(self.github_client.config().filesystem.preserve_timestamps.unwrap_or(TimestampSource::Commit))
+    }
+
+    /// Timestamps for archive entries; entries without one get the current time
+    async fn archive_times(&self, github_url: &GitHubUrl, plan: &DownloadPlan) -> Result<HashMap<String, SystemTime>> {
+        let preserve = self.github_client.config().filesystem.preserve_timestamps;
+        let repo_paths: Vec<String> = plan.writes().map(|file| file.repo_path.clone()).collect();
+        let source = preserve.unwrap_or(TimestampSource::Commit);
+
+        match self.file_downloader.file_times(github_url, &repo_paths, source).await {
+            Ok(times) => Ok(times),
+            Err(e) if e.is_access_error() => Err(e),
+            // Only worth a warning when timestamps were asked for
+            Err(e) if preserve.is_some() => {
+                warn!("Not preserving timestamps: {}", e);
+                Ok(HashMap::new())
+            }
+            Err(e) => {
+                debug!("Using the current time for archive entries: {}", e);
+                Ok(HashMap::new())
+            }
+        }
+    }
+
+    /// What previous copies wrote to `destination`; empty without a cache
+    fn copy_state(&self, destination: &Path) -> CopyState {
+        let Some(cache) = self.github_client.cache() else {
+            return CopyState::default();
+        };
+        cache.get_copy_state(destination).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable copy state: {}", e);
+            None
+        }).unwrap_or_default()
+    }
+
+    /// Remember what this copy left in `destination`, so the next run can skip it
+    fn save_copy_state(&self, github_url: &GitHubUrl, destination: &Path, report: &DownloadReport) {
+        let Some(cache) = self.github_client.cache() else {
+            return;
+        };
+
+        let root = github_url.path.as_deref().unwrap_or("").trim_matches('/');
+        let mut state = self.copy_state(destination);
+        for file in &report.files {
+            // A renamed file doesn't live at the path the next run looks at
+            if !matches!(file.conflict, ConflictResolution::Renamed(_)) {
+                state.record(relative_path(root, &file.repo_path), &file.sha, &file.local_path);
+            }
+        }
+        for path in &report.deleted {
+            if let Ok(relative) = path.strip_prefix(destination) {
+                state.files.remove(&relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+
+        if let Err(e) = cache.put_copy_state(destination, &state) {
+            warn!("Failed to save copy state: {}", e);
+        }
+    }
+
+    /// Remove written symlinks that lead outside `destination` through other links
+    ///
+    /// Each target was checked on its own while planning, but a link to a link can
+    /// still resolve elsewhere. Links whose target doesn't exist are kept.
+    fn remove_escaping_links(&self, destination: &Path, report: &mut DownloadReport) {
+        let (links, files): (Vec<DownloadedFile>, Vec<DownloadedFile>) = std::mem::take(&mut report.files)
+            .into_iter()
+            .partition(|file| std::fs::symlink_metadata(&file.local_path).is_ok_and(|metadata| metadata.file_type().is_symlink()));
+        report.files = files;
+
+        for link in links {
+            if is_within(destination, &link.local_path).unwrap_or(true) {
+                report.files.push(link);
+                continue;
+            }
+
+            let e = GcpError::InvalidPath {
+                path: format!("symlink {} resolves outside {}", link.local_path.display(), destination.display()),
+            };
+            warn!("Removing {}", e);
+            if let Err(e) = std::fs::remove_file(&link.local_path) {
+                warn!("Failed to remove {}: {}", link.local_path.display(), e);
+            }
+            let failed = FailedFile::new(&link.repo_path, &e);
+            if let Some(ref events) = self.events {
+                events(DownloadEvent::Failed(&failed));
+            }
+            report.errors.push(failed);
+        }
+    }
+
+    /// Remove the files a plan deletes, then any directories that leaves empty
+    ///
+    /// Each path is checked to still resolve inside `destination` right before it
+    /// is removed.
+    fn delete_extraneous(&self, destination: &Path, deletions: &[std::path::PathBuf], report: &mut DownloadReport) {
+        for path in deletions {
+            let result = match path.parent().map_or(Ok(false), |dir| is_within(destination, dir)) {
+                // Only the directory is resolved, so a link is removed wherever it points
+                Ok(true) => std::fs::remove_file(path).map_err(|e| GcpError::FileIo { path: path.clone(), source: e }),
+                Ok(false) => Err(GcpError::InvalidPath { path: format!("{} is outside {}", path.display(), destination.display()) }),
+                Err(e) => Err(GcpError::FileIo { path: path.clone(), source: e }),
+            };
+
+            match result {
+                Ok(()) => {
+                    debug!("Deleted {}", path.display());
+                    if let Some(ref events) = self.events {
+                        events(DownloadEvent::Deleted(path));
+                    }
+                    report.deleted.push(path.clone());
+
+                    // Fails, and stops, at the first directory that isn't empty
+                    for dir in path.ancestors().skip(1).take_while(|dir| *dir != destination) {
+                        if std::fs::remove_dir(dir).is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to delete {}: {}", path.display(), e);
+                    let failed = FailedFile::new(&path.display().to_string(), &e);
+                    if let Some(ref events) = self.events {
+                        events(DownloadEvent::Failed(&failed));
+                    }
+                    report.errors.push(failed);
+                }
+            }
+        }
+    }
+
+    /// List a folder and, depth first, everything below it
+    ///
+    /// Each directory is immediately followed by its own contents, which is the
+    /// order a tree view needs.
+    pub fn list_recursive<'a>(
+        &'a self,
+        github_url: &'a GitHubUrl,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<Vec<GitHubFile>>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut entries = Vec::new();
+            for item in self.get_folder_contents(github_url).await? {
+                let is_directory = item.is_directory();
+                let folder_url = GitHubUrl {
+                    host: github_url.host.clone(),
+                    owner: github_url.owner.clone(),
+                    repo: github_url.repo.clone(),
+                    path: Some(item.path.clone()),
+                    ref_: github_url.ref_.clone(),
+                    url_type: crate::github::UrlType::Folder,
+                };
+                entries.push(item);
+
+                if is_directory {
+                    entries.extend(self.list_recursive(&folder_url).await?);
+                }
+            }
+            Ok(entries)
+        })
+    }
+
+    /// Compare the remote folder with `local_dir` by git blob SHA
+    ///
+    /// Only listings are fetched; file contents are never downloaded.
+    pub async fn diff(&self, github_url: &GitHubUrl, local_dir: &Path) -> Result<Vec<DiffEntry>> {
+        if github_url.url_type != crate::github::UrlType::Folder {
+            return Err(GcpError::InvalidOperation {
+                operation: "diff".to_string(),
+                reason: format!("URL type is not a folder: {:?}", github_url.url_type),
+            });
+        }
+
+        let config = &self.github_client.config().download;
+        let filter = PathFilter::new(&config.include, &config.exclude)?;
+        let remote = self.list_recursive(github_url).await?;
+        let local = local_blob_shas(local_dir).map_err(|e| GcpError::FileIo { path: local_dir.to_path_buf(), source: e })?;
+
+        Ok(diff::compare(github_url.path.as_deref().unwrap_or(""), &remote, &local, &filter))
+    }
+
     /// Get folder contents from GitHub API
-    async fn get_folder_contents(&self, github_url: &GitHubUrl) -> Result<Vec<GitHubFile>> {
+    pub async fn get_folder_contents(&self, github_url: &GitHubUrl) -> Result<Vec<GitHubFile>> {
         debug!("Getting folder contents for: {}/{}@{}",
                github_url.owner,
                github_url.repo,
@@ -133,48 +658,22 @@ impl FolderDownloader {
         let path = github_url.path.as_deref().unwrap_or("");
         let ref_ = github_url.ref_.as_deref().unwrap_or("main");
 
-        // Use GitHub API to list directory contents
-        let handler = self.github_client.client.repos(&github_url.owner, &github_url.repo);
-
-        // Try to get content using the GitHub Contents API
-        match handler.get_content().path(path).r#ref(ref_).send().await {
-            Ok(contents) => {
-                debug!("Successfully fetched folder contents, found {} items", contents.items.len());
-
-                // Convert octocrab Content items to our GitHubFile format
-                let mut github_files = Vec::new();
-
-                for item in contents.items {
-                    let github_file = GitHubFile {
-                        name: item.name,
-                        path: item.path,
-                        sha: item.sha,
-                        size: item.size as i64,
-                        url: item.url,
-                        html_url: item.html_url.unwrap_or_else(|| "".to_string()),
-                        git_url: item.git_url.unwrap_or_else(|| "".to_string()),
-                        download_url: item.download_url,
-                        file_type: item.r#type,
-                        content: item.content,
-                        encoding: item.encoding,
-                    };
-                    github_files.push(github_file);
-                }
-
-                Ok(github_files)
-            }
+        match self.github_client.list_contents(&github_url.owner, &github_url.repo, path, Some(ref_)).await {
+            Ok(github_files) => Ok(github_files),
+            // Nothing to fall back to when the cache is the only source
+            Err(e @ GcpError::NotCached { .. }) => Err(e),
+            // A fallback listing can't fix credentials, a missing path or a rate limit
+            Err(e @ GcpError::RateLimit { .. }) => Err(e),
+            Err(e) if e.is_auth_error() || e.is_not_found() => Err(e),
             Err(e) => {
                 error!("Failed to get folder contents from GitHub API: {}", e);
 
                 // Fallback: Try to construct from raw URL if possible
-                if let Some(raw_url) = github_url.raw_url() {
+                if github_url.raw_url().is_some() {
                     warn!("GitHub API failed, attempting fallback approach for folder");
                     self.get_folder_contents_fallback(github_url).await
                 } else {
-                    Err(GcpError::GitHubApi {
-                        status: 0,
-                        message: format!("Failed to get folder contents: {}", e),
-                    })
+                    Err(e)
                 }
             }
         }
@@ -214,7 +713,7 @@ impl FolderDownloader {
     }
 
     /// Estimate total files in folder for progress reporting
-    fn estimate_file_count<'a>(
+    pub fn estimate_file_count<'a>(
         &'a self,
         github_url: &'a GitHubUrl,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<usize>> + Send + 'a>> {
@@ -228,6 +727,7 @@ impl FolderDownloader {
                         } else if item.is_directory() {
                             // Recursively count files in subdirectories
                             let folder_url = GitHubUrl {
+                                host: github_url.host.clone(),
                                 owner: github_url.owner.clone(),
                                 repo: github_url.repo.clone(),
                                 path: Some(item.path.clone()),
@@ -243,4 +743,64 @@ impl FolderDownloader {
             }
         })
     }
-}
\ No newline at end of file
+}
+
+/// What stays the same while planning each folder of a copy
+struct PlanScope<'a> {
+    /// Repository path of the folder being copied
+    root: &'a str,
+    force: bool,
+    filter: &'a PathFilter,
+    state: &'a CopyState,
+    /// Files go into an archive, so nothing at the destination matters
+    archive: bool,
+}
+
+
+/// Create the symlink a plan entry describes, replacing an existing file when overwriting
+fn write_symlink(item: &PlannedFile, target: &str) -> Result<DownloadedFile> {
+    let path = item.target_path();
+    create_intermediate_dirs(&path)?;
+    // A link that exists but dangles would otherwise block the new one
+    let existing = std::fs::symlink_metadata(&path);
+    if item.action == PlannedAction::Overwrite || existing.is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        std::fs::remove_file(&path).or_else(|e| if e.kind() == std::io::ErrorKind::NotFound { Ok(()) } else { Err(e) })
+            .map_err(|e| GcpError::FileIo { path: path.clone(), source: e })?;
+    }
+    create_symlink(&path, target)?;
+
+    Ok(DownloadedFile {
+        repo_path: item.repo_path.clone(),
+        local_path: path,
+        size: target.len() as u64,
+        sha: item.sha.clone(),
+        conflict: item.action.conflict_resolution().unwrap_or(ConflictResolution::NoConflict),
+    })
+}
+
+/// Local files below `destination` that the plan doesn't account for
+///
+/// Files outside the `--include`/`--exclude` selection are kept, as are files in
+/// the place of a skipped remote symlink or submodule.
+fn plan_deletions(root: &str, destination: &Path, filter: &PathFilter, plan: &DownloadPlan) -> Result<Vec<std::path::PathBuf>> {
+    let remote: std::collections::HashSet<&str> = plan.files.iter()
+        .map(|file| relative_path(root, &file.repo_path))
+        .collect();
+    // Only the paths matter here, so nothing is read or hashed
+    let local = local_paths(destination).map_err(|e| GcpError::FileIo { path: destination.to_path_buf(), source: e })?;
+
+    Ok(local.into_iter()
+        .filter(|path| !remote.contains(path.as_str()) && diff::is_selected(filter, path))
+        .map(|path| destination.join(path))
+        .collect())
+}
+
+/// `path` relative to the folder being copied
+pub(crate) fn relative_path<'a>(root: &str, path: &'a str) -> &'a str {
+    if root.is_empty() {
+        return path;
+    }
+    path.strip_prefix(root)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .unwrap_or(path)
+}
@@ -1,7 +1,15 @@
+pub mod diff;
 pub mod file;
 pub mod folder;
+pub mod plan;
 pub mod progress;
+pub mod report;
+pub mod state;
 
+pub use diff::{DiffEntry, DiffStatus};
 pub use file::FileDownloader;
 pub use folder::FolderDownloader;
-pub use progress::ProgressReporter;
\ No newline at end of file
+pub use plan::{DownloadPlan, PlannedAction, PlannedFile, SkipReason};
+pub use progress::ProgressReporter;
+pub use report::{DownloadEvent, DownloadReport, DownloadedFile, EventSink, FailedFile};
+pub use state::{CopiedFile, CopyState};
\ No newline at end of file
@@ -0,0 +1,160 @@
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+
+use crate::filesystem::{resolve_conflict, ConflictResolution};
+
+/// What a download would do with one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PlannedAction {
+    Create,
+    Overwrite,
+    /// The destination exists; the file is written under `to` instead
+    Rename { to: PathBuf },
+    Skip { reason: SkipReason },
+}
+
+impl PlannedAction {
+    /// Apply the conflict policy to `destination`: overwrite with `force`, otherwise
+    /// write next to an existing file under a new name
+    pub fn for_destination(destination: &Path, force: bool) -> Self {
+        if !destination.exists() {
+            PlannedAction::Create
+        } else if force {
+            PlannedAction::Overwrite
+        } else {
+            PlannedAction::Rename { to: resolve_conflict(destination) }
+        }
+    }
+
+    /// Where a write to `destination` ends up, after any rename
+    pub fn target_path(&self, destination: &Path) -> PathBuf {
+        match self {
+            PlannedAction::Rename { to } => to.clone(),
+            _ => destination.to_path_buf(),
+        }
+    }
+
+    /// How a write resolves its conflict; `None` for a skip
+    pub fn conflict_resolution(&self) -> Option<ConflictResolution> {
+        match self {
+            PlannedAction::Create => Some(ConflictResolution::NoConflict),
+            PlannedAction::Overwrite => Some(ConflictResolution::Overwritten),
+            PlannedAction::Rename { to } => Some(ConflictResolution::Renamed(to.display().to_string())),
+            PlannedAction::Skip { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Matched an `--exclude` pattern
+    Excluded,
+    /// Matched none of the `--include` patterns
+    NotIncluded,
+    /// Larger than `DownloadConfig::max_file_size`
+    TooLarge,
+    Submodule,
+    Symlink,
+    /// The destination already has identical content
+    Unchanged,
+    /// A symlink whose target lies outside the destination
+    UnsafeSymlink,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    pub repo_path: String,
+    pub local_path: PathBuf,
+    pub size: u64,
+    pub sha: String,
+    #[serde(flatten)]
+    pub action: PlannedAction,
+    /// Content is already in the blob cache, so writing it needs no request
+    pub cached: bool,
+    /// Recreated as a symlink to this target, relative to the link
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symlink: Option<String>,
+    /// A symlink copied as a regular file, with the content of this repository path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub copied_from: Option<String>,
+}
+
+impl PlannedFile {
+    pub fn is_write(&self) -> bool {
+        !matches!(self.action, PlannedAction::Skip { .. })
+    }
+
+    /// Where the file ends up, after any rename
+    pub fn target_path(&self) -> PathBuf {
+        self.action.target_path(&self.local_path)
+    }
+
+    /// Repository path the written content comes from
+    pub fn content_path(&self) -> &str {
+        self.copied_from.as_deref().unwrap_or(&self.repo_path)
+    }
+}
+
+/// The files a download would write or skip, resolved against the destination
+///
+/// Building a plan lists the source but never touches the destination, which
+/// is what `--dry-run` prints.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DownloadPlan {
+    pub files: Vec<PlannedFile>,
+    /// Contents API requests made while listing the source
+    pub listing_requests: usize,
+    /// Blob requests made while listing the source, to read symlink targets
+    pub symlink_requests: usize,
+    /// Requests for file modes and modification times, made once something is written
+    pub metadata_requests: usize,
+    /// Local files not in the source, removed with `--delete`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deletions: Vec<PathBuf>,
+}
+
+impl DownloadPlan {
+    /// Bytes that would be written
+    pub fn total_bytes(&self) -> u64 {
+        self.writes().map(|file| file.size).sum()
+    }
+
+    /// Requests needed to fetch the content of the files to write
+    pub fn download_requests(&self) -> usize {
+        self.writes().filter(|file| !file.cached).count()
+    }
+
+    /// Every request the download takes: listings, symlink targets, metadata and content fetches
+    pub fn api_calls(&self) -> usize {
+        self.listing_requests + self.symlink_requests + self.metadata_requests + self.download_requests()
+    }
+
+    pub fn writes(&self) -> impl Iterator<Item = &PlannedFile> {
+        self.files.iter().filter(|file| file.is_write())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflict_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("a.txt");
+        std::fs::write(&existing, b"x").unwrap();
+
+        assert_eq!(PlannedAction::for_destination(&dir.path().join("new.txt"), false), PlannedAction::Create);
+        assert_eq!(PlannedAction::for_destination(&existing, true), PlannedAction::Overwrite);
+        assert_eq!(
+            PlannedAction::for_destination(&existing, false),
+            PlannedAction::Rename { to: dir.path().join("a_1.txt") }
+        );
+        assert_eq!(
+            PlannedAction::for_destination(&existing, false).conflict_resolution(),
+            Some(ConflictResolution::Renamed(dir.path().join("a_1.txt").display().to_string()))
+        );
+    }
+}
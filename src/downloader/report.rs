@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::Serialize;
+
+use crate::error::GcpError;
+use crate::filesystem::ConflictResolution;
+
+/// A file written to disk by a download
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadedFile {
+    /// Path of the file within the repository
+    pub repo_path: String,
+    pub local_path: PathBuf,
+    pub size: u64,
+    /// Git blob SHA of the content written
+    pub sha: String,
+    pub conflict: ConflictResolution,
+}
+
+/// A file that could not be downloaded; the rest of the folder still is
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedFile {
+    pub repo_path: String,
+    /// `GcpError::kind` of the failure
+    pub kind: &'static str,
+    pub message: String,
+}
+
+impl FailedFile {
+    pub fn new(repo_path: &str, error: &GcpError) -> Self {
+        Self {
+            repo_path: repo_path.to_string(),
+            kind: error.kind(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Per-file outcome, reported as soon as each file completes
+#[derive(Debug, Clone, Copy)]
+pub enum DownloadEvent<'a> {
+    Downloaded(&'a DownloadedFile),
+    Failed(&'a FailedFile),
+    /// An extraneous local file was removed with `--delete`
+    Deleted(&'a Path),
+}
+
+/// Callback receiving `DownloadEvent`s, e.g. to stream them as NDJSON
+pub type EventSink = Arc<dyn Fn(DownloadEvent<'_>) + Send + Sync>;
+
+/// Everything a download wrote, and everything it could not
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DownloadReport {
+    pub files: Vec<DownloadedFile>,
+    pub errors: Vec<FailedFile>,
+    /// Local files removed with `--delete`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<PathBuf>,
+}
+
+impl DownloadReport {
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    /// Whether some, but not necessarily all, files failed
+    pub fn is_partial(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+
+/// What previous folder copies wrote to a destination
+///
+/// Kept in the cache, keyed by destination. A file whose remote blob SHA is the
+/// one recorded here, and whose local size and modification time are unchanged
+/// since it was written, is skipped without being read or downloaded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CopyState {
+    /// Keyed by `/`-separated path relative to the destination
+    pub files: BTreeMap<String, CopiedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopiedFile {
+    /// Git blob SHA of the content written
+    pub sha: String,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+impl CopyState {
+    /// Whether `local` still holds exactly the content of blob `sha` written by a previous copy
+    pub fn is_unchanged(&self, path: &str, sha: &str, local: &Path) -> bool {
+        let Some(copied) = self.files.get(path) else {
+            return false;
+        };
+        copied.sha == sha && CopiedFile::stat(sha, local).is_some_and(|current| current == *copied)
+    }
+
+    /// Record that `local` was just written with the content of blob `sha`
+    pub fn record(&mut self, path: &str, sha: &str, local: &Path) {
+        match CopiedFile::stat(sha, local) {
+            Some(copied) => {
+                self.files.insert(path.to_string(), copied);
+            }
+            None => {
+                self.files.remove(path);
+            }
+        }
+    }
+}
+
+impl CopiedFile {
+    fn stat(sha: &str, local: &Path) -> Option<Self> {
+        // A symlink's metadata would be its target's
+        let metadata = std::fs::symlink_metadata(local).ok().filter(|metadata| metadata.is_file())?;
+        Some(Self {
+            sha: sha.to_string(),
+            size: metadata.len(),
+            modified: metadata.modified().ok()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unchanged_until_edited() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = dir.path().join("a.txt");
+        std::fs::write(&local, b"hello\n").unwrap();
+
+        let mut state = CopyState::default();
+        assert!(!state.is_unchanged("a.txt", "abc", &local));
+
+        state.record("a.txt", "abc", &local);
+        assert!(state.is_unchanged("a.txt", "abc", &local));
+        assert!(!state.is_unchanged("a.txt", "def", &local));
+
+        std::fs::write(&local, b"edited, and longer\n").unwrap();
+        assert!(!state.is_unchanged("a.txt", "abc", &local));
+
+        std::fs::remove_file(&local).unwrap();
+        state.record("a.txt", "abc", &local);
+        assert!(state.files.is_empty());
+    }
+}
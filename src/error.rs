@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -7,8 +8,8 @@ pub enum GcpError {
     #[error("Invalid GitHub URL: {url}")]
     InvalidUrl { url: String },
 
-    #[error("GitHub API error: {message} (status: {status})")]
-    GitHubApi { status: u16, message: String },
+    #[error("GitHub API error: {message} (status: {status}){}", documentation_suffix(.documentation_url))]
+    GitHubApi { status: u16, message: String, documentation_url: Option<String> },
 
     #[error("Rate limit exceeded. Resets at: {reset_time}")]
     RateLimit { reset_time: DateTime<Utc> },
@@ -49,16 +50,110 @@ pub enum GcpError {
     #[error("Operation cancelled")]
     Cancelled,
 
+    #[error("Not available offline: {item} is not in the local cache")]
+    NotCached { item: String },
+
     #[error("File IO error: {path} - {source}")]
     FileIo { path: PathBuf, #[source] source: std::io::Error },
 
     #[error("Invalid operation: {operation} - {reason}")]
     InvalidOperation { operation: String, reason: String },
+
+    #[error("{path} does not match gcp.lock: expected blob {expected}, got {actual}")]
+    LockMismatch { path: String, expected: String, actual: String },
 }
 
 pub type Result<T> = std::result::Result<T, GcpError>;
 
+/// Process exit codes, stable across releases so scripts can branch on them
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// Any error without a more specific category
+    pub const FAILURE: i32 = 1;
+    /// Invalid arguments, URL or configuration (clap uses the same code)
+    pub const USAGE: i32 = 2;
+    pub const AUTH: i32 = 3;
+    pub const NOT_FOUND: i32 = 4;
+    pub const RATE_LIMIT: i32 = 5;
+    pub const FILESYSTEM: i32 = 6;
+    /// The copy finished but some files failed
+    pub const PARTIAL_FAILURE: i32 = 7;
+    /// Interrupted, following the shell's 128 + SIGINT convention
+    pub const CANCELLED: i32 = 130;
+}
+
+/// How long to back off from a secondary rate limit that gives no `Retry-After`,
+/// per GitHub's guidance to wait at least a minute
+pub const SECONDARY_RATE_LIMIT_WAIT_SECONDS: i64 = 60;
+
+fn documentation_suffix(documentation_url: &Option<String>) -> String {
+    documentation_url
+        .as_deref()
+        .map(|url| format!(" - see {}", url))
+        .unwrap_or_default()
+}
+
 impl GcpError {
+    /// Build the error for a failed GitHub response
+    ///
+    /// Rate limiting becomes `RateLimit` (see [`rate_limit_reset`]); anything else
+    /// is `GitHubApi` with the real status.
+    pub fn from_github_response(
+        status: u16,
+        message: String,
+        documentation_url: Option<String>,
+        headers: &HeaderMap,
+        now: DateTime<Utc>,
+    ) -> Self {
+        match rate_limit_reset(status, headers, now) {
+            Some(reset_time) => GcpError::RateLimit { reset_time },
+            None => GcpError::GitHubApi { status, message, documentation_url },
+        }
+    }
+
+    /// Stable, machine-readable name of the error variant
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GcpError::InvalidUrl { .. } => "invalid_url",
+            GcpError::GitHubApi { .. } => "github_api",
+            GcpError::RateLimit { .. } => "rate_limit",
+            GcpError::Authentication { .. } => "authentication",
+            GcpError::FileSystem { .. } => "file_system",
+            GcpError::Network { .. } => "network",
+            GcpError::DownloadFailed { .. } => "download_failed",
+            GcpError::FileConflict { .. } => "file_conflict",
+            GcpError::Io { .. } => "io",
+            GcpError::UrlParse { .. } => "url_parse",
+            GcpError::Json { .. } => "json",
+            GcpError::Config { .. } => "config",
+            GcpError::InvalidPath { .. } => "invalid_path",
+            GcpError::FileTooLarge { .. } => "file_too_large",
+            GcpError::Cancelled => "cancelled",
+            GcpError::NotCached { .. } => "not_cached",
+            GcpError::FileIo { .. } => "file_io",
+            GcpError::InvalidOperation { .. } => "invalid_operation",
+            GcpError::LockMismatch { .. } => "lock_mismatch",
+        }
+    }
+
+    /// Process exit code for the error's category (see [`exit_code`])
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            e if e.is_auth_error() => exit_code::AUTH,
+            e if e.is_not_found() => exit_code::NOT_FOUND,
+            GcpError::NotCached { .. } => exit_code::NOT_FOUND,
+            GcpError::RateLimit { .. } | GcpError::GitHubApi { status: 429, .. } => exit_code::RATE_LIMIT,
+            GcpError::FileSystem { .. }
+            | GcpError::FileIo { .. }
+            | GcpError::Io { .. }
+            | GcpError::FileConflict { .. }
+            | GcpError::InvalidPath { .. } => exit_code::FILESYSTEM,
+            GcpError::InvalidUrl { .. } | GcpError::UrlParse { .. } | GcpError::Config { .. } => exit_code::USAGE,
+            GcpError::Cancelled => exit_code::CANCELLED,
+            _ => exit_code::FAILURE,
+        }
+    }
+
     pub fn is_retryable(&self) -> bool {
         match self {
             GcpError::Network { .. } => true,
@@ -79,10 +174,98 @@ impl GcpError {
         }
     }
 
+    /// Rate limits and rejected credentials, which every further request would hit too
+    pub fn is_access_error(&self) -> bool {
+        self.is_auth_error() || matches!(self, GcpError::RateLimit { .. } | GcpError::GitHubApi { status: 429, .. })
+    }
+
     pub fn is_not_found(&self) -> bool {
         match self {
             GcpError::GitHubApi { status, .. } => *status == 404,
             _ => false,
         }
     }
-}
\ No newline at end of file
+}
+
+/// When a rate-limited request may be retried, or `None` if the response is not rate limiting
+///
+/// A 403 or 429 with `Retry-After` is a secondary rate limit; one with
+/// `x-ratelimit-remaining: 0` has exhausted the primary limit until `x-ratelimit-reset`.
+/// A bare 429 is treated as a secondary limit.
+pub fn rate_limit_reset(status: u16, headers: &HeaderMap, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if status != 403 && status != 429 {
+        return None;
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+
+    if let Some(seconds) = header("retry-after").and_then(|v| v.parse::<i64>().ok()) {
+        return Some(now + chrono::Duration::seconds(seconds));
+    }
+    if header("x-ratelimit-remaining") == Some("0") {
+        if let Some(reset) = header("x-ratelimit-reset")
+            .and_then(|v| v.parse::<i64>().ok())
+            .and_then(|epoch| DateTime::from_timestamp(epoch, 0))
+        {
+            return Some(reset);
+        }
+    }
+    if status == 429 {
+        return Some(now + chrono::Duration::seconds(SECONDARY_RATE_LIMIT_WAIT_SECONDS));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_rate_limit_mapping() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        let secondary = GcpError::from_github_response(403, "slow down".into(), None, &headers(&[("retry-after", "30")]), now);
+        assert!(matches!(secondary, GcpError::RateLimit { reset_time } if reset_time == now + chrono::Duration::seconds(30)));
+
+        let primary = headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1700000600")]);
+        assert_eq!(rate_limit_reset(403, &primary, now), DateTime::from_timestamp(1_700_000_600, 0));
+        assert!(rate_limit_reset(429, &HeaderMap::new(), now).is_some());
+
+        let forbidden = GcpError::from_github_response(
+            403,
+            "Resource not accessible by integration".into(),
+            Some("https://docs.github.com/rest".into()),
+            &headers(&[("x-ratelimit-remaining", "4999")]),
+            now,
+        );
+        assert!(forbidden.is_auth_error());
+        assert!(forbidden.to_string().ends_with("(status: 403) - see https://docs.github.com/rest"));
+        assert!(rate_limit_reset(404, &headers(&[("retry-after", "30")]), now).is_none());
+    }
+
+    #[test]
+    fn test_exit_codes_by_category() {
+        let api = |status| GcpError::GitHubApi { status, message: String::new(), documentation_url: None };
+
+        assert_eq!(api(401).exit_code(), exit_code::AUTH);
+        assert_eq!(GcpError::Authentication { reason: String::new() }.exit_code(), exit_code::AUTH);
+        assert_eq!(api(404).exit_code(), exit_code::NOT_FOUND);
+        assert_eq!(api(429).exit_code(), exit_code::RATE_LIMIT);
+        assert_eq!(api(500).exit_code(), exit_code::FAILURE);
+        assert_eq!(GcpError::Cancelled.exit_code(), exit_code::CANCELLED);
+        assert_eq!(
+            GcpError::FileIo { path: PathBuf::from("x"), source: std::io::ErrorKind::PermissionDenied.into() }.exit_code(),
+            exit_code::FILESYSTEM
+        );
+        assert_eq!(api(404).kind(), "github_api");
+    }
+}
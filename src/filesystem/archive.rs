@@ -0,0 +1,262 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use flate2::write::GzEncoder;
+use zip::write::SimpleFileOptions;
+
+use crate::error::{GcpError, Result};
+use super::writer::{FileMetadata, OutputSink};
+
+/// Archive formats `--archive` can write, chosen by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(ArchiveFormat::TarGz)
+        } else if name.ends_with(".tar") {
+            Ok(ArchiveFormat::Tar)
+        } else if name.ends_with(".zip") {
+            Ok(ArchiveFormat::Zip)
+        } else {
+            Err(GcpError::Config {
+                message: format!("Unsupported archive {}; use .tar, .tar.gz, .tgz or .zip", path.display()),
+            })
+        }
+    }
+}
+
+enum Archive {
+    Tar(tar::Builder<File>),
+    TarGz(tar::Builder<GzEncoder<File>>),
+    Zip(zip::ZipWriter<File>),
+}
+
+/// Streams files into a tar, tar.gz or zip archive instead of a directory
+///
+/// The archive is built in a temporary file next to `path` and only moved into
+/// place by `finish`; dropping the writer without finishing discards it, so a
+/// failed copy never leaves a truncated or incomplete archive behind.
+pub struct ArchiveWriter {
+    path: PathBuf,
+    temp: Option<tempfile::NamedTempFile>,
+    archive: Option<Archive>,
+}
+
+impl ArchiveWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let format = ArchiveFormat::from_path(path)?;
+        let io_err = |e: std::io::Error| GcpError::FileIo { path: path.to_path_buf(), source: e };
+
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        std::fs::create_dir_all(dir).map_err(io_err)?;
+        let temp = tempfile::NamedTempFile::new_in(dir).map_err(io_err)?;
+        let file = temp.as_file().try_clone().map_err(io_err)?;
+
+        let archive = match format {
+            ArchiveFormat::Tar => Archive::Tar(tar::Builder::new(file)),
+            ArchiveFormat::TarGz => Archive::TarGz(tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()))),
+            ArchiveFormat::Zip => Archive::Zip(zip::ZipWriter::new(file)),
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            temp: Some(temp),
+            archive: Some(archive),
+        })
+    }
+
+    fn io_error(&self, source: std::io::Error) -> GcpError {
+        GcpError::FileIo { path: self.path.clone(), source }
+    }
+}
+
+impl OutputSink for ArchiveWriter {
+    fn write_file(&mut self, path: &Path, content: &[u8], metadata: &FileMetadata) -> Result<()> {
+        // Archives always use '/' whatever the platform
+        let name = path.to_string_lossy().replace('\\', "/");
+        let result = match self.archive.as_mut() {
+            Some(Archive::Tar(builder)) => append_tar(builder, &name, content, metadata),
+            Some(Archive::TarGz(builder)) => append_tar(builder, &name, content, metadata),
+            Some(Archive::Zip(zip)) => {
+                let options = SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Deflated)
+                    .unix_permissions(metadata.mode)
+                    .last_modified_time(zip_time(metadata.modified));
+                zip.start_file(name, options)
+                    .map_err(std::io::Error::other)
+                    .and_then(|()| zip.write_all(content))
+            }
+            None => Err(std::io::Error::other("archive already finished")),
+        };
+        result.map_err(|e| self.io_error(e))
+    }
+
+    fn write_symlink(&mut self, path: &Path, target: &str, metadata: &FileMetadata) -> Result<()> {
+        let name = path.to_string_lossy().replace('\\', "/");
+        let result = match self.archive.as_mut() {
+            Some(Archive::Tar(builder)) => append_tar_link(builder, &name, target, metadata),
+            Some(Archive::TarGz(builder)) => append_tar_link(builder, &name, target, metadata),
+            Some(Archive::Zip(zip)) => {
+                let options = SimpleFileOptions::default().last_modified_time(zip_time(metadata.modified));
+                zip.add_symlink(name, target, options).map_err(std::io::Error::other)
+            }
+            None => Err(std::io::Error::other("archive already finished")),
+        };
+        result.map_err(|e| self.io_error(e))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let result = match self.archive.take() {
+            Some(Archive::Tar(builder)) => builder.into_inner().and_then(|mut file| file.flush()),
+            Some(Archive::TarGz(builder)) => builder.into_inner()
+                .and_then(GzEncoder::finish)
+                .and_then(|mut file| file.flush()),
+            Some(Archive::Zip(zip)) => zip.finish()
+                .map_err(std::io::Error::other)
+                .and_then(|mut file| file.flush()),
+            None => return Ok(()),
+        };
+        result.map_err(|e| self.io_error(e))?;
+
+        if let Some(temp) = self.temp.take() {
+            // Temporary files are created owner-only
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(temp.path(), std::fs::Permissions::from_mode(0o644))
+                    .map_err(|e| self.io_error(e))?;
+            }
+            temp.persist(&self.path).map_err(|e| self.io_error(e.error))?;
+        }
+        Ok(())
+    }
+}
+
+fn append_tar<W: Write>(builder: &mut tar::Builder<W>, name: &str, content: &[u8], metadata: &FileMetadata) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(content.len() as u64);
+    header.set_mode(metadata.mode);
+    header.set_mtime(tar_time(metadata.modified));
+    builder.append_data(&mut header, name, content)
+}
+
+fn append_tar_link<W: Write>(builder: &mut tar::Builder<W>, name: &str, target: &str, metadata: &FileMetadata) -> std::io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    header.set_mode(0o777);
+    header.set_mtime(tar_time(metadata.modified));
+    builder.append_link(&mut header, name, target)
+}
+
+/// Seconds since the epoch; entries without a time get the current one
+fn tar_time(modified: Option<SystemTime>) -> u64 {
+    let modified = modified.unwrap_or_else(SystemTime::now);
+    modified.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Zip timestamps have no time zone and start in 1980; UTC is recorded
+fn zip_time(modified: Option<SystemTime>) -> zip::DateTime {
+    let time: DateTime<Utc> = modified.unwrap_or_else(SystemTime::now).into();
+    zip::DateTime::from_date_and_time(
+        u16::try_from(time.year()).unwrap_or(1980),
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    ).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn metadata(mode: u32) -> FileMetadata {
+        FileMetadata {
+            mode,
+            modified: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000)),
+        }
+    }
+
+    fn write_archive(path: &Path) {
+        let mut writer = ArchiveWriter::create(path).unwrap();
+        writer.write_file(Path::new("README.md"), b"hello\n", &metadata(0o644)).unwrap();
+        writer.write_file(Path::new("bin/run.sh"), b"#!/bin/sh\n", &metadata(0o755)).unwrap();
+        writer.write_symlink(Path::new("bin/run"), "run.sh", &metadata(0o644)).unwrap();
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_format_from_extension() {
+        assert_eq!(ArchiveFormat::from_path(Path::new("out.tar.gz")).unwrap(), ArchiveFormat::TarGz);
+        assert_eq!(ArchiveFormat::from_path(Path::new("out.TGZ")).unwrap(), ArchiveFormat::TarGz);
+        assert_eq!(ArchiveFormat::from_path(Path::new("dir/out.tar")).unwrap(), ArchiveFormat::Tar);
+        assert_eq!(ArchiveFormat::from_path(Path::new("out.zip")).unwrap(), ArchiveFormat::Zip);
+        assert!(ArchiveFormat::from_path(Path::new("out.rar")).is_err());
+    }
+
+    #[test]
+    fn test_tar_gz_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.tar.gz");
+        write_archive(&path);
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(&path).unwrap()));
+        let mut entries = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            if entry.header().entry_type().is_symlink() {
+                assert_eq!(entry.path().unwrap(), Path::new("bin/run"));
+                assert_eq!(entry.link_name().unwrap().unwrap(), Path::new("run.sh"));
+                continue;
+            }
+            let mut content = String::new();
+            entry.read_to_string(&mut content).unwrap();
+            let header = entry.header();
+            entries.push((entry.path().unwrap().display().to_string(), header.mode().unwrap(), header.mtime().unwrap(), content));
+        }
+        assert_eq!(entries, [
+            ("README.md".to_string(), 0o644, 1_700_000_000, "hello\n".to_string()),
+            ("bin/run.sh".to_string(), 0o755, 1_700_000_000, "#!/bin/sh\n".to_string()),
+        ]);
+        // Only the archive itself is left in the directory
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_zip_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.zip");
+        write_archive(&path);
+
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut script = archive.by_name("bin/run.sh").unwrap();
+        let mut content = String::new();
+        script.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "#!/bin/sh\n");
+        assert_eq!(script.unix_mode().unwrap() & 0o777, 0o755);
+        let modified = script.last_modified().unwrap();
+        assert_eq!((modified.year(), modified.month(), modified.day()), (2023, 11, 14));
+        drop(script);
+
+        let mut link = archive.by_name("bin/run").unwrap();
+        assert!(link.is_symlink());
+        let mut target = String::new();
+        link.read_to_string(&mut target).unwrap();
+        assert_eq!(target, "run.sh");
+    }
+}
@@ -0,0 +1,84 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+use crate::error::{GcpError, Result};
+
+/// `--include` / `--exclude` glob patterns
+///
+/// Patterns are matched against a path relative to the folder being copied and
+/// against its file name, so `*.md`, `README.md` and `docs/**` all work as expected.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: build_set(include)?,
+            exclude: build_set(exclude)?,
+        })
+    }
+
+    /// Whether `path` matches an `--exclude` pattern
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.as_ref().is_some_and(|set| matches(set, path))
+    }
+
+    /// Whether `path` matches an `--include` pattern (everything does without any)
+    pub fn is_included(&self, path: &str) -> bool {
+        match &self.include {
+            Some(set) => matches(set, path),
+            None => true,
+        }
+    }
+}
+
+fn matches(set: &GlobSet, path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    set.is_match(path) || set.is_match(name)
+}
+
+fn build_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| GcpError::Config {
+            message: format!("Invalid glob pattern '{}': {}", pattern, e),
+        })?;
+        builder.add(glob);
+    }
+
+    builder.build().map(Some).map_err(|e| GcpError::Config {
+        message: format!("Invalid glob patterns: {}", e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_and_exclude() {
+        let filter = PathFilter::new(
+            &["*.rs".to_string(), "docs/**".to_string()],
+            &["*_test.rs".to_string(), "target".to_string()],
+        ).unwrap();
+
+        assert!(filter.is_included("src/lib.rs"));
+        assert!(filter.is_included("docs/guide/intro.md"));
+        assert!(!filter.is_included("README.md"));
+
+        assert!(filter.is_excluded("src/parser_test.rs"));
+        assert!(filter.is_excluded("target"));
+        assert!(!filter.is_excluded("src/lib.rs"));
+
+        let everything = PathFilter::default();
+        assert!(everything.is_included("any/file") && !everything.is_excluded("any/file"));
+
+        assert!(PathFilter::new(&["[".to_string()], &[]).is_err());
+    }
+}
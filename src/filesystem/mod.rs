@@ -1,5 +1,9 @@
+pub mod archive;
+pub mod filter;
 pub mod writer;
 pub mod utils;
 
-pub use writer::{FileWriter, ConflictResolution};
-pub use utils::*;
\ No newline at end of file
+pub use archive::{ArchiveFormat, ArchiveWriter};
+pub use filter::PathFilter;
+pub use writer::{create_symlink, write_with_metadata, FileMetadata, FileWriter, ConflictResolution, OutputSink};
+pub use utils::*;
@@ -1,9 +1,10 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
 use std::fs;
 use std::io;
 
 /// Create intermediate directories for the given file path
-pub fn create_intermediate_dirs(path: &PathBuf) -> io::Result<()> {
+pub fn create_intermediate_dirs(path: &Path) -> io::Result<()> {
     if let Some(parent) = path.parent() {
         if !parent.exists() {
             fs::create_dir_all(parent)?;
@@ -13,9 +14,9 @@ pub fn create_intermediate_dirs(path: &PathBuf) -> io::Result<()> {
 }
 
 /// Resolve file conflicts by auto-renaming (append number)
-pub fn resolve_conflict(path: &PathBuf) -> PathBuf {
+pub fn resolve_conflict(path: &Path) -> PathBuf {
     if !path.exists() {
-        return path.clone();
+        return path.to_path_buf();
     }
 
     let mut counter = 1;
@@ -41,7 +42,7 @@ pub fn resolve_conflict(path: &PathBuf) -> PathBuf {
         }
     }
 
-    path.clone()
+    path.to_path_buf()
 }
 
 /// Validate that a path is safe to write to
@@ -57,13 +58,13 @@ pub fn validate_safe_path(path: &Path) -> io::Result<()> {
 }
 
 /// Ensure the destination directory exists and is writable
-pub fn ensure_destination_dir(dest: &PathBuf) -> io::Result<()> {
+pub fn ensure_destination_dir(dest: &Path) -> io::Result<()> {
     validate_safe_path(dest)?;
 
     if dest.is_file() {
         // If destination is a file, its parent must be a directory
         if let Some(parent) = dest.parent() {
-            create_intermediate_dirs(&parent.to_path_buf())?;
+            create_intermediate_dirs(parent)?;
         }
     } else {
         // Create directory if it doesn't exist
@@ -73,6 +74,108 @@ pub fn ensure_destination_dir(dest: &PathBuf) -> io::Result<()> {
     Ok(())
 }
 
+/// Compute the git blob SHA-1 of the given content, as GitHub reports it
+pub fn git_blob_sha(content: &[u8]) -> String {
+    use sha1::{Digest, Sha1};
+
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Whether the file at `path` exists with exactly the content of blob `sha`
+pub fn matches_blob_sha(path: &Path, sha: &str) -> bool {
+    path.is_file() && fs::read(path).is_ok_and(|content| git_blob_sha(&content) == sha)
+}
+
+/// Whether the existing `path` resolves to somewhere inside the existing `root`
+///
+/// Both are canonicalized, so `..` components and symlinked directories can't
+/// lead outside.
+pub fn is_within(root: &Path, path: &Path) -> io::Result<bool> {
+    Ok(path.canonicalize()?.starts_with(root.canonicalize()?))
+}
+
+/// Where a symlink at `link` points, given its `target` as stored in git
+///
+/// Both `link` and the result are `/`-separated and relative to the same root.
+/// `None` when `target` is absolute or its `..` components climb out of the root.
+pub fn resolve_link_target(link: &str, target: &str) -> Option<String> {
+    // A backslash would be a separator on Windows, where the components below don't apply
+    if target.is_empty() || target.starts_with('/') || target.contains('\\') {
+        return None;
+    }
+
+    let mut components: Vec<&str> = link.split('/').filter(|component| !component.is_empty()).collect();
+    components.pop()?;
+    for component in target.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            component => components.push(component),
+        }
+    }
+    Some(components.join("/"))
+}
+
+/// Git blob SHAs of every file and symlink below `dir`, keyed by `/`-separated relative path
+///
+/// As in git, a symlink's SHA is that of its target path, so links compare by
+/// where they point. `.git` directories are not descended into. A missing `dir`
+/// yields an empty map.
+pub fn local_blob_shas(dir: &Path) -> io::Result<BTreeMap<String, String>> {
+    let mut shas = BTreeMap::new();
+    walk_files(dir, "", &mut |relative, path, file_type| {
+        let content = if file_type.is_symlink() {
+            fs::read_link(path)?.to_string_lossy().into_owned().into_bytes()
+        } else {
+            fs::read(path)?
+        };
+        shas.insert(relative, git_blob_sha(&content));
+        Ok(())
+    })?;
+    Ok(shas)
+}
+
+/// Relative paths of every file and symlink below `dir`, as [`local_blob_shas`]
+/// lists them but without reading any content
+pub fn local_paths(dir: &Path) -> io::Result<BTreeSet<String>> {
+    let mut paths = BTreeSet::new();
+    walk_files(dir, "", &mut |relative, _, _| {
+        paths.insert(relative);
+        Ok(())
+    })?;
+    Ok(paths)
+}
+
+/// Receives the relative path, full path and type of each entry [`walk_files`] finds
+type Visit<'a> = dyn FnMut(String, &Path, fs::FileType) -> io::Result<()> + 'a;
+
+/// Call `visit` for each file and symlink below `dir`, skipping `.git`; a missing `dir` has none
+fn walk_files(dir: &Path, prefix: &str, visit: &mut Visit<'_>) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            if name != ".git" {
+                walk_files(&entry.path(), &relative, visit)?;
+            }
+        } else if file_type.is_file() || file_type.is_symlink() {
+            visit(relative, &entry.path(), file_type)?;
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +211,70 @@ mod tests {
 
         assert!(deep_path.parent().unwrap().exists());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_git_blob_sha_matches_git() {
+        // `printf 'hello\n' | git hash-object --stdin`
+        assert_eq!(git_blob_sha(b"hello\n"), "ce013625030ba8dba906f756967f9e9ca394464a");
+        assert_eq!(git_blob_sha(b""), "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391");
+    }
+
+    #[test]
+    fn test_local_blob_shas() {
+        let dir = tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("sub")).unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join("a.txt"), b"hello\n").unwrap();
+        fs::write(dir.path().join("sub/b.txt"), b"").unwrap();
+        fs::write(dir.path().join(".git/HEAD"), b"ref").unwrap();
+
+        let shas = local_blob_shas(dir.path()).unwrap();
+        assert_eq!(shas.keys().collect::<Vec<_>>(), ["a.txt", "sub/b.txt"]);
+        assert_eq!(shas["a.txt"], "ce013625030ba8dba906f756967f9e9ca394464a");
+        assert!(local_paths(dir.path()).unwrap().iter().eq(shas.keys()));
+        assert!(local_blob_shas(&dir.path().join("missing")).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_local_blob_shas_includes_symlinks() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), b"hello\n").unwrap();
+        std::os::unix::fs::symlink("a.txt", dir.path().join("link")).unwrap();
+        std::os::unix::fs::symlink("../gone", dir.path().join("dangling")).unwrap();
+
+        // Compared by target, like git, and listed even when dangling
+        let shas = local_blob_shas(dir.path()).unwrap();
+        assert_eq!(shas.keys().collect::<Vec<_>>(), ["a.txt", "dangling", "link"]);
+        assert_eq!(shas["link"], git_blob_sha(b"a.txt"));
+        assert_eq!(shas["dangling"], git_blob_sha(b"../gone"));
+    }
+
+    #[test]
+    fn test_blob_match_and_containment() {
+        let dir = tempdir().unwrap();
+        let inside = dir.path().join("dest");
+        fs::create_dir_all(&inside).unwrap();
+        fs::write(inside.join("a.txt"), b"hello\n").unwrap();
+        fs::write(dir.path().join("b.txt"), b"").unwrap();
+
+        assert!(matches_blob_sha(&inside.join("a.txt"), "ce013625030ba8dba906f756967f9e9ca394464a"));
+        assert!(!matches_blob_sha(&inside.join("a.txt"), "0000"));
+        assert!(!matches_blob_sha(&inside.join("missing.txt"), "ce013625030ba8dba906f756967f9e9ca394464a"));
+
+        assert!(is_within(&inside, &inside.join("a.txt")).unwrap());
+        assert!(!is_within(&inside, &inside.join("../b.txt")).unwrap());
+    }
+
+    #[test]
+    fn test_resolve_link_target() {
+        assert_eq!(resolve_link_target("docs/link", "guide.md").as_deref(), Some("docs/guide.md"));
+        assert_eq!(resolve_link_target("docs/link", "./sub/../guide.md").as_deref(), Some("docs/guide.md"));
+        assert_eq!(resolve_link_target("docs/link", "../README.md").as_deref(), Some("README.md"));
+        assert_eq!(resolve_link_target("link", "docs").as_deref(), Some("docs"));
+        assert_eq!(resolve_link_target("docs/link", "../../etc/passwd"), None);
+        assert_eq!(resolve_link_target("link", ".."), None);
+        assert_eq!(resolve_link_target("link", "/etc/passwd"), None);
+        assert_eq!(resolve_link_target("link", "C:\\Windows"), None);
+    }
+}
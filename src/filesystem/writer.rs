@@ -1,10 +1,154 @@
-// TODO: Implement file writer with conflict resolution
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use serde::Serialize;
+
+use crate::error::{GcpError, Result};
+use super::create_intermediate_dirs;
+
+/// How a file is recorded besides its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// Unix permission bits, e.g. `0o644`
+    pub mode: u32,
+    pub modified: Option<SystemTime>,
+}
+
+impl FileMetadata {
+    /// Permissions for a blob with git mode `git_mode`, e.g. `0o100755`
+    ///
+    /// Executable blobs get `0755`; everything else `default_permissions`, or
+    /// `0644` when there is no default.
+    pub fn from_git_mode(git_mode: Option<u32>, default_permissions: Option<u32>) -> Self {
+        let mode = if git_mode.is_some_and(|mode| mode & 0o111 != 0) {
+            0o755
+        } else {
+            default_permissions.unwrap_or(0o644)
+        };
+        Self { mode, modified: None }
+    }
+}
+
+/// Where downloaded files end up: a directory tree or an archive
+pub trait OutputSink: Send {
+    /// Write one file at `path`, relative to the root of the output
+    fn write_file(&mut self, path: &Path, content: &[u8], metadata: &FileMetadata) -> Result<()>;
+
+    /// Record a symlink at `path` pointing to `target`, as stored in git
+    fn write_symlink(&mut self, path: &Path, target: &str, metadata: &FileMetadata) -> Result<()>;
+
+    /// Complete the output once every file is written
+    fn finish(&mut self) -> Result<()>;
+}
+
+/// Writes files below a root directory on disk
 pub struct FileWriter {
-    // Implementation to be added
+    root: PathBuf,
+}
+
+impl FileWriter {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl OutputSink for FileWriter {
+    fn write_file(&mut self, path: &Path, content: &[u8], metadata: &FileMetadata) -> Result<()> {
+        let destination = self.root.join(path);
+        create_intermediate_dirs(&destination)?;
+        write_with_metadata(&destination, content, metadata)
+    }
+
+    fn write_symlink(&mut self, path: &Path, target: &str, _metadata: &FileMetadata) -> Result<()> {
+        let destination = self.root.join(path);
+        create_intermediate_dirs(&destination)?;
+        create_symlink(&destination, target)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Write `content` to `path`, then apply `metadata`'s permissions and modification time
+///
+/// Permissions are set explicitly, so an overwritten file doesn't keep its old mode.
+pub fn write_with_metadata(path: &Path, content: &[u8], metadata: &FileMetadata) -> Result<()> {
+    let io_error = |e| GcpError::FileIo { path: path.to_path_buf(), source: e };
+
+    std::fs::write(path, content).map_err(io_error)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(metadata.mode)).map_err(io_error)?;
+    }
+    if let Some(modified) = metadata.modified {
+        std::fs::File::options().write(true).open(path)
+            .and_then(|file| file.set_modified(modified))
+            .map_err(io_error)?;
+    }
+    Ok(())
+}
+
+/// Create a symlink at `path` pointing to `target`
+///
+/// Only Unix platforms are supported; elsewhere links are copied instead.
+pub fn create_symlink(path: &Path, target: &str) -> Result<()> {
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, path).map_err(|e| GcpError::FileIo { path: path.to_path_buf(), source: e })
+    }
+    #[cfg(not(unix))]
+    {
+        Err(GcpError::InvalidOperation {
+            operation: "symlink".to_string(),
+            reason: format!("can't create {} -> {} on this platform", path.display(), target),
+        })
+    }
 }
 
+/// How an existing file at the destination was dealt with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ConflictResolution {
     NoConflict,
+    /// Written next to the existing file under this path instead
     Renamed(String),
     Overwritten,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_git_mode() {
+        assert_eq!(FileMetadata::from_git_mode(Some(0o100755), Some(0o600)).mode, 0o755);
+        assert_eq!(FileMetadata::from_git_mode(Some(0o100644), Some(0o600)).mode, 0o600);
+        assert_eq!(FileMetadata::from_git_mode(None, None).mode, 0o644);
+    }
+
+    #[test]
+    fn test_file_writer_creates_parents_and_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let mut writer = FileWriter::new(dir.path());
+        writer.write_file(Path::new("sub/run.sh"), b"#!/bin/sh\n", &FileMetadata { mode: 0o755, modified: Some(modified) }).unwrap();
+        writer.finish().unwrap();
+
+        #[cfg(unix)]
+        {
+            writer.write_symlink(Path::new("sub/link"), "run.sh", &FileMetadata::from_git_mode(None, None)).unwrap();
+            assert_eq!(std::fs::read_link(dir.path().join("sub/link")).unwrap(), Path::new("run.sh"));
+        }
+
+        let path = dir.path().join("sub/run.sh");
+        assert_eq!(std::fs::read(&path).unwrap(), b"#!/bin/sh\n");
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o755);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::path::Path;
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error::{GcpError, Result};
+use crate::github::{AuthSource, Authentication};
+
+/// Installation tokens are refreshed this long before they expire
+const REFRESH_MARGIN_SECONDS: i64 = 5 * 60;
+
+/// Credentials of a GitHub App installation
+#[derive(Clone)]
+pub struct GitHubApp {
+    pub app_id: u64,
+    pub installation_id: u64,
+    key: EncodingKey,
+}
+
+#[derive(Debug, Serialize)]
+struct Claims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for GitHubApp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitHubApp")
+            .field("app_id", &self.app_id)
+            .field("installation_id", &self.installation_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GitHubApp {
+    /// Create app credentials from a PEM-encoded RSA private key
+    pub fn new(app_id: u64, installation_id: u64, private_key_pem: &[u8]) -> Result<Self> {
+        let key = EncodingKey::from_rsa_pem(private_key_pem).map_err(|e| GcpError::Authentication {
+            reason: format!("Invalid GitHub App private key: {}", e),
+        })?;
+        Ok(Self { app_id, installation_id, key })
+    }
+
+    /// Create app credentials reading the private key from a PEM file
+    pub fn from_key_file(app_id: u64, installation_id: u64, path: &Path) -> Result<Self> {
+        let pem = std::fs::read(path).map_err(|e| GcpError::FileIo {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        Self::new(app_id, installation_id, &pem)
+    }
+
+    /// Mint the short-lived RS256 JWT that authenticates as the app itself
+    ///
+    /// `iat` is backdated a minute to tolerate clock drift; GitHub caps `exp` at ten minutes.
+    pub fn jwt(&self, now: DateTime<Utc>) -> Result<String> {
+        let claims = Claims {
+            iat: (now - Duration::seconds(60)).timestamp(),
+            exp: (now + Duration::seconds(9 * 60)).timestamp(),
+            iss: self.app_id.to_string(),
+        };
+
+        jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &self.key).map_err(|e| GcpError::Authentication {
+            reason: format!("Failed to sign GitHub App JWT: {}", e),
+        })
+    }
+
+    /// Exchange the app JWT for an installation access token
+    pub async fn installation_token(&self, api_url: &str) -> Result<Authentication> {
+        let url = format!(
+            "{}/app/installations/{}/access_tokens",
+            api_url.trim_end_matches('/'),
+            self.installation_id
+        );
+        debug!("Requesting installation token from {}", url);
+
+        let response = reqwest::Client::new()
+            .post(&url)
+            .bearer_auth(self.jwt(Utc::now())?)
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .header(reqwest::header::USER_AGENT, concat!("gcp/", env!("CARGO_PKG_VERSION")))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(GcpError::Authentication {
+                reason: format!("GitHub App token exchange failed (HTTP {}): {}", status.as_u16(), body.trim()),
+            });
+        }
+
+        let token: InstallationToken = response.json().await?;
+        debug!("Installation token expires at {}", token.expires_at);
+
+        Ok(Authentication {
+            token: token.token,
+            scopes: vec![],
+            expires_at: Some(token.expires_at),
+            source: AuthSource::GitHubApp {
+                app_id: self.app_id,
+                installation_id: self.installation_id,
+            },
+        })
+    }
+}
+
+impl Authentication {
+    /// Whether the token expires within the refresh margin (tokens without an expiry never do)
+    pub fn needs_refresh(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .map(|expires_at| expires_at - now <= Duration::seconds(REFRESH_MARGIN_SECONDS))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const TEST_KEY: &[u8] = include_bytes!("../../tests/fixtures/github_app_test_key.pem");
+
+    #[test]
+    fn test_jwt_claims() {
+        let app = GitHubApp::new(42, 7, TEST_KEY).unwrap();
+        let now = Utc::now();
+        let jwt = app.jwt(now).unwrap();
+
+        let header = jsonwebtoken::decode_header(&jwt).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+
+        let mut validation = jsonwebtoken::Validation::new(Algorithm::RS256);
+        validation.insecure_disable_signature_validation();
+        validation.required_spec_claims.clear();
+        let claims = jsonwebtoken::decode::<serde_json::Value>(&jwt, &jsonwebtoken::DecodingKey::from_secret(b""), &validation)
+            .unwrap()
+            .claims;
+        assert_eq!(claims["iss"], "42");
+        assert_eq!(claims["iat"], (now - Duration::seconds(60)).timestamp());
+        assert_eq!(claims["exp"], (now + Duration::seconds(540)).timestamp());
+    }
+
+    #[tokio::test]
+    async fn test_installation_token_from_mock_endpoint() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("http://{}", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let n = socket.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]).to_string();
+
+            let body = r#"{"token":"ghs_installation","expires_at":"2030-01-01T00:00:00Z"}"#;
+            let response = format!(
+                "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+
+        let app = GitHubApp::new(42, 7, TEST_KEY).unwrap();
+        let auth = app.installation_token(&api_url).await.unwrap();
+        let request = server.await.unwrap();
+
+        assert!(request.starts_with("POST /app/installations/7/access_tokens "));
+        assert!(request.to_ascii_lowercase().contains("authorization: bearer ey"));
+        assert_eq!(auth.token, "ghs_installation");
+        assert_eq!(auth.source, AuthSource::GitHubApp { app_id: 42, installation_id: 7 });
+        assert!(!auth.needs_refresh(Utc::now()));
+        assert!(auth.needs_refresh(auth.expires_at.unwrap() - Duration::seconds(60)));
+    }
+}
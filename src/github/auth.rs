@@ -1,5 +1,8 @@
 use std::env;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
 use crate::error::{GcpError, Result};
+use crate::settings::ConfigFile;
 
 #[derive(Debug, Clone)]
 pub struct Authentication {
@@ -9,14 +12,77 @@ pub struct Authentication {
     pub source: AuthSource,
 }
 
+/// Outcome of validating a token, cached per token hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenValidation {
+    pub validated_at: chrono::DateTime<chrono::Utc>,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl TokenValidation {
+    pub fn is_fresh(&self, now: chrono::DateTime<chrono::Utc>, ttl_seconds: u64) -> bool {
+        now - self.validated_at < chrono::Duration::seconds(ttl_seconds as i64)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AuthSource {
     Environment,
     CommandLine,
     ConfigFile,
+    /// Token stored by the GitHub CLI in its `hosts.yml`
+    GhCli,
+    /// Password of a matching `machine` entry in the netrc file
+    Netrc,
+    /// Password returned by `git credential fill` for the host
+    GitCredential { host: String, username: Option<String> },
+    /// Installation access token minted for a GitHub App
+    GitHubApp { app_id: u64, installation_id: u64 },
 }
 
 impl Authentication {
+    /// Find a token for `host`, trying each source in order of precedence:
+    /// command line, `GITHUB_TOKEN`, `GH_TOKEN`/`GH_ENTERPRISE_TOKEN`, the gcp
+    /// config file and `gcp auth login` credentials, `~/.netrc`, the gh CLI's
+    /// `hosts.yml`, and finally git's credential helpers
+    pub fn discover(cli_token: Option<String>, host: &str, config: &ConfigFile) -> Result<Option<Self>> {
+        if let Some(token) = cli_token {
+            return Ok(Some(Self::from_token(token)));
+        }
+
+        if let Some(auth) = Self::from_env()? {
+            return Ok(Some(auth));
+        }
+
+        if let Some(auth) = Self::from_gh_env(host)? {
+            return Ok(Some(auth));
+        }
+
+        if let Some(auth) = Self::from_config_file(config, host) {
+            return Ok(Some(auth));
+        }
+
+        if let Some(auth) = Self::from_credentials_store(host)? {
+            return Ok(Some(auth));
+        }
+
+        if let Some(auth) = Self::from_netrc(host)? {
+            return Ok(Some(auth));
+        }
+
+        if let Some(auth) = Self::from_gh_cli(host)? {
+            return Ok(Some(auth));
+        }
+
+        if let Some(auth) = Self::from_git_credential(host)? {
+            return Ok(Some(auth));
+        }
+
+        debug!("No GitHub credentials found for {}", host);
+        Ok(None)
+    }
+
     pub fn from_env() -> Result<Option<Self>> {
         match env::var("GITHUB_TOKEN") {
             Ok(token) => Ok(Some(Authentication {
@@ -32,6 +98,112 @@ impl Authentication {
         }
     }
 
+    /// Read the gh CLI's token variable for `host` (`GH_TOKEN` or `GH_ENTERPRISE_TOKEN`)
+    pub fn from_gh_env(host: &str) -> Result<Option<Self>> {
+        let var = crate::github::gh_cli::token_env_var(host);
+        match env::var(var) {
+            Ok(token) if !token.is_empty() => {
+                debug!("Using token from {}", var);
+                Ok(Some(Authentication {
+                    token,
+                    scopes: vec![],
+                    expires_at: None,
+                    source: AuthSource::Environment,
+                }))
+            }
+            Ok(_) | Err(env::VarError::NotPresent) => Ok(None),
+            Err(e) => Err(GcpError::Config {
+                message: format!("Error reading {}: {}", var, e),
+            }),
+        }
+    }
+
+    /// Reuse the token the gh CLI stored for `host` in its `hosts.yml`
+    pub fn from_gh_cli(host: &str) -> Result<Option<Self>> {
+        let path = match crate::github::gh_cli::hosts_file() {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let token = crate::github::gh_cli::token_from_hosts_file(&path, host)?;
+        Ok(token.map(|token| {
+            debug!("Using gh CLI token from {}", path.display());
+            Authentication {
+                token,
+                scopes: vec![],
+                expires_at: None,
+                source: AuthSource::GhCli,
+            }
+        }))
+    }
+
+    /// Read the password of the netrc entry for `host` (`$NETRC` or `~/.netrc`)
+    pub fn from_netrc(host: &str) -> Result<Option<Self>> {
+        let found = crate::github::netrc::token_for_host(host)?;
+        Ok(found.map(|(token, path)| {
+            debug!("Using token from {}", path.display());
+            Authentication {
+                token,
+                scopes: vec![],
+                expires_at: None,
+                source: AuthSource::Netrc,
+            }
+        }))
+    }
+
+    /// Use whatever credential manager git is configured with (`git credential fill`)
+    pub fn from_git_credential(host: &str) -> Result<Option<Self>> {
+        let credential = crate::github::git_credential::GitCredential::fill(host)?;
+        Ok(credential.map(|credential| {
+            debug!("Using token from git credential helper for {}", host);
+            Authentication {
+                token: credential.password,
+                scopes: vec![],
+                expires_at: None,
+                source: AuthSource::GitCredential {
+                    host: credential.host,
+                    username: credential.username,
+                },
+            }
+        }))
+    }
+
+    /// Report a successful run back to git (`git credential approve`) so the
+    /// helper keeps the credential; a no-op for other sources
+    pub fn approve(&self) -> Result<()> {
+        if let AuthSource::GitCredential { host, username } = &self.source {
+            let credential = crate::github::git_credential::GitCredential {
+                host: host.clone(),
+                username: username.clone(),
+                password: self.token.clone(),
+            };
+            credential.approve()?;
+        }
+        Ok(())
+    }
+
+    /// Look up the token configured for `host` in the config file
+    pub fn from_config_file(config: &ConfigFile, host: &str) -> Option<Self> {
+        let token = config.host(host)?.token.clone()?;
+        Some(Authentication {
+            token,
+            scopes: vec![],
+            expires_at: None,
+            source: AuthSource::ConfigFile,
+        })
+    }
+
+    /// Look up the token saved for `host` by `gcp auth login`
+    pub fn from_credentials_store(host: &str) -> Result<Option<Self>> {
+        let credentials = crate::settings::Credentials::load_default()?;
+        Ok(credentials.get(host).map(|stored| Authentication {
+            token: stored.token.clone(),
+            scopes: stored.scopes.clone(),
+            expires_at: None,
+            source: AuthSource::ConfigFile,
+        }))
+    }
+
     pub fn from_token(token: String) -> Self {
         Authentication {
             token,
@@ -61,6 +233,27 @@ impl Authentication {
         Ok(())
     }
 
+    /// SHA-256 of the token, used to key cached validation results without storing the token
+    pub fn token_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(self.token.as_bytes()))
+    }
+
+    /// Snapshot of what validation learned about the token
+    pub fn validation(&self) -> TokenValidation {
+        TokenValidation {
+            validated_at: chrono::Utc::now(),
+            scopes: self.scopes.clone(),
+            expires_at: self.expires_at,
+        }
+    }
+
+    /// Fill in scopes and expiry from a cached validation
+    pub fn apply_validation(&mut self, validation: &TokenValidation) {
+        self.scopes = validation.scopes.clone();
+        self.expires_at = validation.expires_at;
+    }
+
     pub fn mask_token(&self) -> String {
         if self.token.len() <= 8 {
             return "*".repeat(self.token.len());
@@ -71,23 +264,149 @@ impl Authentication {
         format!("{}...{}", start, end)
     }
 
-    pub async fn validate_with_github(&self, client: &octocrab::Octocrab) -> Result<()> {
+    /// Validate the token against `/user`, recording its scopes and expiry
+    ///
+    /// Classic tokens report their scopes in `X-OAuth-Scopes`; tokens with an expiry
+    /// report it in `GitHub-Authentication-Token-Expiration`.
+    pub async fn validate_with_github(&mut self, client: &octocrab::Octocrab) -> Result<()> {
         // Test the token by making a simple API call
-        match client.current().user().await {
-            Ok(_) => Ok(()),
-            Err(e) => {
-                let error_msg = e.to_string();
-                if error_msg.contains("401") || error_msg.contains("403") {
-                    Err(GcpError::Authentication {
-                        reason: "Invalid or expired GitHub token".to_string(),
-                    })
-                } else {
-                    Err(GcpError::GitHubApi {
-                        status: 0,
-                        message: error_msg,
-                    })
-                }
+        let response = client._get("/user").await.map_err(|e| GcpError::GitHubApi {
+            status: 0,
+            message: e.to_string(),
+            documentation_url: None,
+        })?;
+
+        let status = response.status().as_u16();
+        match status {
+            200..=299 => {}
+            401 => {
+                return Err(GcpError::Authentication {
+                    reason: "Invalid or expired GitHub token".to_string(),
+                });
+            }
+            // Fine-grained tokens without the user-read permission are still valid
+            403 if crate::error::rate_limit_reset(status, response.headers(), chrono::Utc::now()).is_none() => debug!("Token cannot read /user; treating it as valid"),
+            _ => {
+                return Err(GcpError::from_github_response(
+                    status,
+                    "Failed to validate GitHub token".to_string(),
+                    None,
+                    response.headers(),
+                    chrono::Utc::now(),
+                ));
+            }
+        }
+
+        let headers = response.headers();
+        if let Some(scopes) = headers.get("x-oauth-scopes").and_then(|v| v.to_str().ok()) {
+            self.scopes = parse_scopes(scopes);
+            debug!("Token scopes: {:?}", self.scopes);
+        }
+        if let Some(expiration) = headers
+            .get("github-authentication-token-expiration")
+            .and_then(|v| v.to_str().ok())
+        {
+            self.expires_at = parse_token_expiration(expiration);
+            debug!("Token expires at: {:?}", self.expires_at);
+        }
+
+        self.warn_if_expiring(chrono::Utc::now());
+        Ok(())
+    }
+
+    /// Warn when the token expires within `EXPIRY_WARNING_DAYS`
+    pub fn warn_if_expiring(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        match self.expires_at {
+            Some(expires_at) if expires_at - now <= chrono::Duration::days(EXPIRY_WARNING_DAYS) => {
+                let days = (expires_at - now).num_days().max(0);
+                tracing::warn!(
+                    "GitHub token from {:?} expires in {} day(s), on {}",
+                    self.source,
+                    days,
+                    expires_at.format("%Y-%m-%d %H:%M UTC")
+                );
+                true
             }
+            _ => false,
+        }
+    }
+
+    /// Whether the token's known scopes allow reading private repositories
+    ///
+    /// `None` when scopes are unknown (fine-grained and app tokens don't report them).
+    pub fn has_repo_scope(&self) -> Option<bool> {
+        if self.scopes.is_empty() {
+            return None;
+        }
+        Some(self.scopes.iter().any(|scope| scope == "repo"))
+    }
+
+    /// Explain a 404 that may really be GitHub hiding a private repository
+    pub fn not_found_hint(auth: Option<&Self>) -> Option<String> {
+        match auth {
+            None => Some("if the repository is private, provide a GitHub token".to_string()),
+            Some(auth) if auth.has_repo_scope() == Some(false) => Some(format!(
+                "token lacks repo scope (has: {}); private repositories require the `repo` scope",
+                auth.scopes.join(", ")
+            )),
+            Some(_) => None,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Tokens expiring within this many days trigger a warning
+pub const EXPIRY_WARNING_DAYS: i64 = 7;
+
+fn parse_scopes(header: &str) -> Vec<String> {
+    header
+        .split(',')
+        .map(|scope| scope.trim().to_string())
+        .filter(|scope| !scope.is_empty())
+        .collect()
+}
+
+/// Parse `GitHub-Authentication-Token-Expiration`, e.g. `2024-02-03 04:05:06 UTC`
+/// or `2024-02-03 04:05:06 -0800`
+fn parse_token_expiration(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let value = value.trim();
+    if let Some(naive) = value.strip_suffix(" UTC") {
+        return chrono::NaiveDateTime::parse_from_str(naive, "%Y-%m-%d %H:%M:%S")
+            .ok()
+            .map(|dt| dt.and_utc());
+    }
+    chrono::DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S %z")
+        .ok()
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_introspection_headers() {
+        assert_eq!(parse_scopes("repo, read:org,"), vec!["repo".to_string(), "read:org".to_string()]);
+        assert!(parse_scopes("").is_empty());
+
+        let utc = parse_token_expiration("2024-02-03 04:05:06 UTC").unwrap();
+        assert_eq!(utc.to_rfc3339(), "2024-02-03T04:05:06+00:00");
+        let offset = parse_token_expiration("2024-02-03 04:05:06 -0800").unwrap();
+        assert_eq!(offset.to_rfc3339(), "2024-02-03T12:05:06+00:00");
+        assert!(parse_token_expiration("soon").is_none());
+    }
+
+    #[test]
+    fn test_expiry_warning_and_repo_scope_hint() {
+        let now = chrono::Utc::now();
+        let mut auth = Authentication::from_token("ghp_test_token".to_string());
+        assert!(!auth.warn_if_expiring(now));
+        assert_eq!(auth.has_repo_scope(), None);
+        assert!(Authentication::not_found_hint(Some(&auth)).is_none());
+
+        auth.expires_at = Some(now + chrono::Duration::days(2));
+        assert!(auth.warn_if_expiring(now));
+
+        auth.scopes = vec!["public_repo".to_string()];
+        assert!(Authentication::not_found_hint(Some(&auth)).unwrap().contains("lacks repo scope"));
+    }
+}
@@ -1,59 +1,322 @@
 use octocrab::Octocrab;
-use tracing::{debug, info};
-use std::sync::Arc;
+use tracing::{debug, info, warn};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use chrono::{DateTime, Utc};
 
+use crate::cache::Cache;
 use crate::error::{GcpError, Result};
-use crate::github::{RepositoryInfo, Authentication};
+use crate::github::{CommitInfo, GitTree, RepositoryInfo, Authentication, GitHubFile};
+use crate::github::app::GitHubApp;
+
+/// Paths looked up per GraphQL history query; each is one aliased field
+pub const HISTORY_BATCH: usize = 50;
 
 #[derive(Clone)]
 pub struct GitHubClient {
-    pub(crate) client: Arc<Octocrab>,
+    // Swapped out when a GitHub App installation token is refreshed
+    client: Arc<RwLock<Arc<Octocrab>>>,
     config: Arc<crate::Config>,
+    cache: Option<Arc<Cache>>,
+    app: Option<Arc<AppSession>>,
+    auth: Option<Arc<RwLock<TokenState>>>,
+}
+
+/// A personal/OAuth token and whether it has been checked against GitHub yet
+///
+/// Validation is lazy: it happens when a request fails (or on an explicit
+/// `gcp auth check`) rather than costing a `/user` request on every run. A
+/// cached validation fills in scopes and expiry up front, but only a check made
+/// during this run sets `validated`, since the token may have been revoked since.
+struct TokenState {
+    auth: Authentication,
+    validated: bool,
+}
+
+/// A GitHub App installation together with its current installation token
+struct AppSession {
+    app: GitHubApp,
+    auth: tokio::sync::Mutex<Authentication>,
 }
 
 impl GitHubClient {
-    pub async fn new(config: crate::Config, auth: Option<Authentication>) -> Result<Self> {
-        let mut builder = Octocrab::builder();
+    pub async fn new(config: crate::Config, mut auth: Option<Authentication>) -> Result<Self> {
+        let cache = Self::open_cache(&config)?;
 
         if let Some(ref auth) = auth {
             auth.validate_token_format()?;
-            builder = builder.personal_token(auth.token.clone());
             debug!("Using GitHub authentication from source: {:?}", auth.source);
         }
 
-        let client = builder.build()
-            .map_err(|e| GcpError::GitHubApi {
-                status: 0,
-                message: format!("Failed to create GitHub client: {}", e),
-            })?;
+        let client = Self::build_octocrab(&config, auth.as_ref())?;
 
-        // Validate authentication if provided
-        if let Some(ref auth) = auth {
-            auth.validate_with_github(&client).await?;
-            info!("GitHub authentication validated successfully");
+        // Reuse a recent validation (scopes, expiry) without making a request
+        if let (Some(auth), Some(cache)) = (auth.as_mut(), cache.as_deref()) {
+            if let Some(cached) = cache.get_token_validation(&auth.token_hash())? {
+                if cached.is_fresh(chrono::Utc::now(), config.github.auth_cache_ttl_seconds) {
+                    debug!("Using cached token validation from {}", cached.validated_at);
+                    auth.apply_validation(&cached);
+                    auth.warn_if_expiring(chrono::Utc::now());
+                }
+            }
         }
 
         Ok(Self {
-            client: Arc::new(client),
+            client: Arc::new(RwLock::new(Arc::new(client))),
             config: Arc::new(config),
+            cache,
+            app: None,
+            auth: auth.map(|auth| Arc::new(RwLock::new(TokenState { auth, validated: false }))),
         })
     }
 
-    pub async fn get_repository_info(&self, owner: &str, repo: &str) -> Result<RepositoryInfo> {
-        let repo_info = self.client.repos(owner, repo).get().await
+    /// Create a client authenticated as a GitHub App installation
+    ///
+    /// The installation token is minted up front and refreshed automatically
+    /// before its `expires_at`.
+    pub async fn with_app(config: crate::Config, app: GitHubApp) -> Result<Self> {
+        let cache = Self::open_cache(&config)?;
+        if config.cache.offline {
+            return Err(GcpError::Config {
+                message: "GitHub App authentication is not available in offline mode".to_string(),
+            });
+        }
+
+        let auth = app.installation_token(&config.github.api_url).await?;
+        info!("Authenticated as GitHub App {} (installation {})", app.app_id, app.installation_id);
+        let client = Self::build_octocrab(&config, Some(&auth))?;
+
+        Ok(Self {
+            client: Arc::new(RwLock::new(Arc::new(client))),
+            config: Arc::new(config),
+            cache,
+            app: Some(Arc::new(AppSession {
+                app,
+                auth: tokio::sync::Mutex::new(auth),
+            })),
+            auth: None,
+        })
+    }
+
+    fn open_cache(config: &crate::Config) -> Result<Option<Arc<Cache>>> {
+        if config.cache.offline && !config.cache.enabled {
+            return Err(GcpError::Config {
+                message: "Offline mode requires the cache; remove --no-cache".to_string(),
+            });
+        }
+
+        if !config.cache.enabled {
+            return Ok(None);
+        }
+
+        let cache = Cache::from_config(&config.cache)?.for_host(&config.github.host);
+        debug!("Using cache directory: {}", cache.root().display());
+        Ok(Some(Arc::new(cache)))
+    }
+
+    fn build_octocrab(config: &crate::Config, auth: Option<&Authentication>) -> Result<Octocrab> {
+        let mut builder = Octocrab::builder()
+            .base_uri(config.github.api_url.as_str())
+            .map_err(|e| GcpError::Config {
+                message: format!("Invalid GitHub API URL {}: {}", config.github.api_url, e),
+            })?;
+        debug!("Using GitHub API at {}", config.github.api_url);
+
+        if let Some(auth) = auth {
+            builder = builder.personal_token(auth.token.clone());
+        }
+
+        builder.build()
             .map_err(|e| GcpError::GitHubApi {
                 status: 0,
-                message: format!("Failed to get repository info: {}", e),
-            })?;
+                message: format!("Failed to create GitHub client: {}", e),
+                documentation_url: None,
+            })
+    }
+
+    /// Get the octocrab client, refreshing the GitHub App installation token first if it is about to expire
+    pub(crate) async fn octocrab(&self) -> Result<Arc<Octocrab>> {
+        if let Some(session) = &self.app {
+            let mut auth = session.auth.lock().await;
+            if auth.needs_refresh(chrono::Utc::now()) {
+                debug!("Refreshing GitHub App installation token (expires at {:?})", auth.expires_at);
+                let fresh = session.app.installation_token(&self.config.github.api_url).await?;
+                let client = Self::build_octocrab(&self.config, Some(&fresh))?;
+                *self.client.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(client);
+                *auth = fresh;
+            }
+        }
+
+        Ok(self.client.read().unwrap_or_else(|e| e.into_inner()).clone())
+    }
+
+    /// The token authentication in use, with scopes and expiry once validated
+    pub fn authentication(&self) -> Option<Authentication> {
+        self.auth
+            .as_ref()
+            .map(|state| state.read().unwrap_or_else(|e| e.into_inner()).auth.clone())
+    }
+
+    /// Validate the token against GitHub, filling in its scopes and expiry
+    ///
+    /// With `use_cache`, a validation cached within the configured TTL is reused.
+    /// Returns `Ok(None)` when the client is unauthenticated.
+    pub async fn validate_authentication(&self, use_cache: bool) -> Result<Option<Authentication>> {
+        let state = match &self.auth {
+            Some(state) => state,
+            None => return Ok(None),
+        };
+        let mut auth = state.read().unwrap_or_else(|e| e.into_inner()).auth.clone();
+        let hash = auth.token_hash();
+
+        if use_cache {
+            if let Some(cached) = self.cache().map(|cache| cache.get_token_validation(&hash)).transpose()?.flatten() {
+                if cached.is_fresh(chrono::Utc::now(), self.config.github.auth_cache_ttl_seconds) {
+                    debug!("Using cached token validation from {}", cached.validated_at);
+                    auth.apply_validation(&cached);
+                    return Ok(Some(auth));
+                }
+            }
+        }
+
+        auth.validate_with_github(&*self.octocrab().await?).await?;
+        info!("GitHub authentication validated successfully");
+
+        if let Some(cache) = self.cache() {
+            if let Err(e) = cache.put_token_validation(&hash, &auth.validation()) {
+                warn!("Failed to cache token validation: {}", e);
+            }
+        }
+
+        let mut state = state.write().unwrap_or_else(|e| e.into_inner());
+        state.auth = auth.clone();
+        state.validated = true;
+        Ok(Some(auth))
+    }
+
+    /// Send a request to `uri`, relative to the API root or absolute, and decode the response
+    ///
+    /// The request is a POST of `body` as JSON when there is one, otherwise a GET.
+    /// Requests go through octocrab's raw `_get`/`_post` rather than its typed
+    /// handlers, so a failed response keeps its headers for [`Self::api_error`].
+    async fn api_request<R: octocrab::FromResponse>(&self, uri: &str, body: Option<&serde_json::Value>, context: &str) -> Result<R> {
+        let octocrab = self.octocrab().await?;
+        let response = match body {
+            Some(body) => octocrab._post(uri, Some(body)).await,
+            None => octocrab._get(uri).await,
+        };
+        let response = response.map_err(|e| transport_error(e, context))?;
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let headers = response.headers().clone();
+            let body = octocrab.body_to_string(response).await.unwrap_or_default();
+            return Err(self.api_error(status, &headers, &body, context).await);
+        }
+        R::from_response(response).await.map_err(|e| transport_error(e, context))
+    }
+
+    /// Convert a failed GitHub response into a `GcpError`
+    ///
+    /// GitHub errors keep their real status, message and documentation URL; rate
+    /// limiting, as read from the response headers, becomes `RateLimit`. A 401, the
+    /// first 403, and the first 404 while the token's scopes are unknown trigger
+    /// token validation against GitHub, so a bad token is reported as such; a 404
+    /// is annotated with the likely cause when the token can't see private repositories.
+    async fn api_error(&self, status: u16, headers: &reqwest::header::HeaderMap, body: &str, context: &str) -> GcpError {
+        let body: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+        let github_message = body["message"].as_str().unwrap_or("no message").to_string();
+        let documentation_url = body["documentation_url"].as_str().map(str::to_string);
+
+        let now = chrono::Utc::now();
+        if let Some(reset_time) = crate::error::rate_limit_reset(status, headers, now) {
+            warn!("Hit GitHub's rate limit; it resets at {}", reset_time);
+            return GcpError::RateLimit { reset_time };
+        }
+        // GitHub asks for at least a minute's wait when a secondary limit sends neither header
+        if status == 403 && github_message.to_ascii_lowercase().contains("secondary rate limit") {
+            warn!("Hit GitHub's secondary rate limit");
+            return GcpError::RateLimit {
+                reset_time: now + chrono::Duration::seconds(crate::error::SECONDARY_RATE_LIMIT_WAIT_SECONDS),
+            };
+        }
+
+        let state = self.auth.as_ref().map(|state| {
+            let state = state.read().unwrap_or_else(|e| e.into_inner());
+            (state.validated, state.auth.scopes.is_empty())
+        });
+        let revalidate = match (state, status) {
+            (None, _) => false,
+            (Some(_), 401) => true,
+            (Some((validated, _)), 403) => !validated,
+            (Some((validated, scopes_unknown)), 404) => !validated && scopes_unknown,
+            _ => false,
+        };
+        if revalidate {
+            debug!("Got HTTP {}; validating token", status);
+            match self.validate_authentication(false).await {
+                Err(e @ GcpError::Authentication { .. }) => return e,
+                Ok(Some(auth)) => {
+                    auth.warn_if_expiring(chrono::Utc::now());
+                }
+                _ => {}
+            }
+        }
+
+        let mut message = format!("{}: {}", context, github_message);
+        if status == 404 {
+            if let Some(hint) = Authentication::not_found_hint(self.authentication().as_ref()) {
+                message = format!("{} ({})", message, hint);
+            }
+        }
+
+        GcpError::GitHubApi {
+            status,
+            message,
+            documentation_url,
+        }
+    }
+
+    pub fn config(&self) -> &crate::Config {
+        &self.config
+    }
+
+    /// Whether requests carry a token or GitHub App credentials
+    pub fn is_authenticated(&self) -> bool {
+        self.auth.is_some() || self.app.is_some()
+    }
+
+    /// Whether requests must be answered from the local cache only
+    pub fn is_offline(&self) -> bool {
+        self.config.cache.offline
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_deref()
+    }
+
+    pub async fn get_repository_info(&self, owner: &str, repo: &str) -> Result<RepositoryInfo> {
+        if self.is_offline() {
+            return self.cache()
+                .map(|cache| cache.get_repository_info(owner, repo))
+                .transpose()?
+                .flatten()
+                .ok_or_else(|| GcpError::NotCached {
+                    item: format!("repository info for {}/{}", owner, repo),
+                });
+        }
+
+        let route = format!("/repos/{}/{}", owner, repo);
+        let repo_info: octocrab::models::Repository = self.api_request(&route, None, "Failed to get repository info").await?;
 
-        Ok(RepositoryInfo {
+        let info = RepositoryInfo {
             id: repo_info.id.0 as i64,
             name: repo_info.name,
             full_name: repo_info.full_name.unwrap_or_else(|| format!("{}/{}", owner, repo)),
             description: repo_info.description,
             private: repo_info.private.unwrap_or(false),
             fork: repo_info.fork.unwrap_or(false),
-            html_url: repo_info.html_url.map(|u| u.to_string()).unwrap_or_else(|| format!("https://github.com/{}/{}", owner, repo)),
+            html_url: repo_info.html_url.map(|u| u.to_string()).unwrap_or_else(|| format!("https://{}/{}/{}", self.config.github.host, owner, repo)),
             default_branch: repo_info.default_branch.unwrap_or_else(|| "main".to_string()),
             size: repo_info.size.map(|s| s as i64).unwrap_or(0),
             stargazers_count: repo_info.stargazers_count.map(|s| s as i64).unwrap_or(0),
@@ -62,16 +325,328 @@ impl GitHubClient {
                 serde_json::Value::String(s) => Some(s),
                 _ => None,
             }),
-            created_at: repo_info.created_at.unwrap_or_else(|| chrono::Utc::now()),
-            updated_at: repo_info.updated_at.unwrap_or_else(|| chrono::Utc::now()),
-            pushed_at: repo_info.pushed_at.unwrap_or_else(|| chrono::Utc::now()),
-        })
+            created_at: repo_info.created_at.unwrap_or_else(chrono::Utc::now),
+            updated_at: repo_info.updated_at.unwrap_or_else(chrono::Utc::now),
+            pushed_at: repo_info.pushed_at.unwrap_or_else(chrono::Utc::now),
+            license: repo_info.license.map(|license| match license.spdx_id.as_str() {
+                "" | "NOASSERTION" => license.name,
+                _ => license.spdx_id,
+            }),
+        };
+
+        if let Some(cache) = self.cache() {
+            if let Err(e) = cache.put_repository_info(owner, repo, &info) {
+                warn!("Failed to cache repository info: {}", e);
+            }
+        }
+
+        Ok(info)
+    }
+
+    /// Resolve a branch, tag or SHA to the full SHA of the commit it points at
+    pub async fn resolve_commit_sha(&self, owner: &str, repo: &str, ref_: Option<&str>) -> Result<String> {
+        self.resolve_commit(owner, repo, ref_).await.map(|commit| commit.sha)
+    }
+
+    /// Resolve a branch, tag or SHA to the commit it points at
+    pub async fn resolve_commit(&self, owner: &str, repo: &str, ref_: Option<&str>) -> Result<CommitInfo> {
+        let ref_ = ref_.unwrap_or("main");
+        if self.is_offline() {
+            return Err(GcpError::NotCached {
+                item: format!("commit for {}/{}@{}", owner, repo, ref_),
+            });
+        }
+
+        let route = format!("/repos/{}/{}/commits/{}", owner, repo, ref_);
+        let commit = self.api_request(&route, None, "Failed to resolve commit").await?;
+        Ok(commit_info(commit))
+    }
+
+    /// Everything below `path` at `ref_`, or with `recursive` off only the
+    /// directory's own entries, listed with one Git Trees API request
+    ///
+    /// Unlike the Contents API, tree entries carry file modes. Entry paths are
+    /// returned relative to the repository root, like listings.
+    pub async fn get_tree(&self, owner: &str, repo: &str, path: &str, ref_: Option<&str>, recursive: bool) -> Result<GitTree> {
+        let ref_ = ref_.unwrap_or("main");
+        let path = path.trim_matches('/');
+
+        if self.is_offline() {
+            let not_cached = || GcpError::NotCached {
+                item: format!("tree of {}/{}/{}@{}", owner, repo, path, ref_),
+            };
+            let cache = self.cache().ok_or_else(not_cached)?;
+            return cache.get_tree(owner, repo, ref_, path, recursive)?.ok_or_else(not_cached);
+        }
+
+        // `<ref>:<path>` names the subtree, so only the part being copied is listed
+        let tree_ish = if path.is_empty() { ref_.to_string() } else { format!("{}:{}", ref_, path) };
+        let route = format!("/repos/{}/{}/git/trees/{}{}", owner, repo, tree_ish, if recursive { "?recursive=1" } else { "" });
+        let mut tree: GitTree = self.api_request(&route, None, "Failed to get tree").await?;
+        if tree.truncated {
+            warn!("Tree of {}/{}/{}@{} is too large to list completely; some file modes fall back to defaults", owner, repo, path, ref_);
+        }
+        if !path.is_empty() {
+            for entry in &mut tree.tree {
+                entry.path = format!("{}/{}", path, entry.path);
+            }
+        }
+
+        if let Some(cache) = self.cache() {
+            if let Err(e) = cache.put_tree(owner, repo, ref_, path, recursive, &tree) {
+                warn!("Failed to cache tree: {}", e);
+            }
+        }
+        Ok(tree)
+    }
+
+    /// The most recent commit on `ref_` that touched `path`, if any
+    pub async fn last_commit(&self, owner: &str, repo: &str, path: &str, ref_: Option<&str>) -> Result<Option<CommitInfo>> {
+        let ref_ = ref_.unwrap_or("main");
+        if self.is_offline() {
+            return Err(GcpError::NotCached {
+                item: format!("history of {}/{}/{}@{}", owner, repo, path, ref_),
+            });
+        }
+
+        let mut query = vec![("sha", ref_), ("per_page", "1")];
+        if !path.is_empty() {
+            query.push(("path", path));
+        }
+        let route = format!("/repos/{}/{}/commits?{}", owner, repo, query_string(&query));
+        let commits: Vec<octocrab::models::repos::RepoCommit> = self.api_request(&route, None, "Failed to list commits").await?;
+
+        Ok(commits.into_iter().next().map(commit_info))
+    }
+
+    /// Committer date of the last commit on `ref_` that touched each of `paths`
+    ///
+    /// Paths are looked up with GraphQL, [`HISTORY_BATCH`] per request. GraphQL
+    /// needs a token, so without one (or when it fails) each path costs one
+    /// commits API request instead. Paths without history are left out; rate
+    /// limits and rejected credentials are returned rather than fallen back from.
+    pub async fn last_commit_dates(&self, owner: &str, repo: &str, paths: &[String], ref_: Option<&str>) -> Result<HashMap<String, DateTime<Utc>>> {
+        let ref_ = ref_.unwrap_or("main");
+        if self.is_offline() {
+            return Err(GcpError::NotCached {
+                item: format!("history of {}/{}@{}", owner, repo, ref_),
+            });
+        }
+
+        let mut dates = HashMap::new();
+        let mut graphql = self.is_authenticated();
+        for batch in paths.chunks(HISTORY_BATCH) {
+            if graphql {
+                match self.history_batch(owner, repo, ref_, batch).await {
+                    Ok(batch_dates) => {
+                        dates.extend(batch_dates);
+                        continue;
+                    }
+                    Err(e) if e.is_access_error() => return Err(e),
+                    Err(e) => {
+                        debug!("GraphQL history query failed, using the commits API per file: {}", e);
+                        graphql = false;
+                    }
+                }
+            }
+
+            for path in batch {
+                if let Some(commit) = self.last_commit(owner, repo, path, Some(ref_)).await? {
+                    if let Some(date) = commit.committer_date.or(commit.date) {
+                        dates.insert(path.clone(), date);
+                    }
+                }
+            }
+        }
+        Ok(dates)
+    }
+
+    /// One GraphQL request for the last commit dates of up to [`HISTORY_BATCH`] paths
+    async fn history_batch(&self, owner: &str, repo: &str, ref_: &str, paths: &[String]) -> Result<HashMap<String, DateTime<Utc>>> {
+        let body = serde_json::json!({
+            "query": history_query(paths),
+            "variables": { "owner": owner, "name": repo, "ref": ref_ },
+        });
+        let url = graphql_url(&self.config.github.api_url);
+        let response: serde_json::Value = self.api_request(&url, Some(&body), "GraphQL history query failed").await?;
+
+        let Some(commit) = response.pointer("/data/repository/object").filter(|commit| commit.is_object()) else {
+            // GraphQL answers errors with 200 OK
+            return Err(GcpError::GitHubApi {
+                status: 200,
+                message: format!("GraphQL history query failed: {}", response.get("errors").unwrap_or(&response)),
+                documentation_url: None,
+            });
+        };
+
+        let mut dates = HashMap::new();
+        for (index, path) in paths.iter().enumerate() {
+            let date = commit
+                .pointer(&format!("/f{}/nodes/0/committedDate", index))
+                .and_then(|date| date.as_str())
+                .and_then(|date| DateTime::parse_from_rfc3339(date).ok());
+            if let Some(date) = date {
+                dates.insert(path.clone(), date.with_timezone(&Utc));
+            }
+        }
+        Ok(dates)
+    }
+
+    /// List the contents of a repository path via the Contents API
+    ///
+    /// Listings are written through to the metadata cache, and served from it
+    /// exclusively in offline mode.
+    pub async fn list_contents(&self, owner: &str, repo: &str, path: &str, ref_: Option<&str>) -> Result<Vec<GitHubFile>> {
+        let ref_ = ref_.unwrap_or("main");
+
+        if self.is_offline() {
+            let not_cached = || GcpError::NotCached {
+                item: format!("listing of {}/{}/{}@{}", owner, repo, path, ref_),
+            };
+            let cache = self.cache().ok_or_else(not_cached)?;
+            if let Some(items) = cache.get_listing(owner, repo, ref_, path)? {
+                return Ok(items);
+            }
+            // Like the API, listing a file's path yields the file itself
+            return cache.get_file_entry(owner, repo, ref_, path)?
+                .map(|entry| vec![entry])
+                .ok_or_else(not_cached);
+        }
+
+        let route = format!("/repos/{}/{}/contents/{}?{}", owner, repo, path, query_string(&[("ref", ref_)]));
+        let contents: octocrab::models::repos::ContentItems = self.api_request(&route, None, "Failed to get folder contents").await?;
+
+        debug!("Successfully fetched contents of {}, found {} items", path, contents.items.len());
+
+        // Convert octocrab Content items to our GitHubFile format
+        let items: Vec<GitHubFile> = contents.items
+            .into_iter()
+            .map(|item| GitHubFile {
+                name: item.name,
+                path: item.path,
+                sha: item.sha,
+                size: item.size,
+                url: item.url,
+                html_url: item.html_url.unwrap_or_default(),
+                git_url: item.git_url.unwrap_or_default(),
+                download_url: item.download_url,
+                file_type: item.r#type,
+                content: item.content,
+                encoding: item.encoding,
+            })
+            .collect();
+
+        if let Some(cache) = self.cache() {
+            if let Err(e) = cache.put_listing(owner, repo, ref_, path, &items) {
+                warn!("Failed to cache listing for {}: {}", path, e);
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// The content of blob `sha`, from the blob cache or the Git Blobs API
+    ///
+    /// Blobs are addressed by content, so a cached one is current even online.
+    pub async fn get_blob(&self, owner: &str, repo: &str, sha: &str) -> Result<Vec<u8>> {
+        if let Some(content) = self.cache().map(|cache| cache.get_blob(sha)).transpose()?.flatten() {
+            return Ok(content);
+        }
+        if self.is_offline() {
+            return Err(GcpError::NotCached {
+                item: format!("blob {} of {}/{}", sha, owner, repo),
+            });
+        }
+
+        let route = format!("/repos/{}/{}/git/blobs/{}", owner, repo, sha);
+        let blob: serde_json::Value = self.api_request(&route, None, "Failed to get blob").await?;
+
+        use base64::{Engine as _, engine::general_purpose::STANDARD};
+        let encoded = blob["content"].as_str().unwrap_or_default();
+        let content = match blob["encoding"].as_str() {
+            // Base64 content comes wrapped at 60 columns
+            Some("base64") => STANDARD.decode(encoded.split_whitespace().collect::<String>()).ok(),
+            Some("utf-8") => Some(encoded.as_bytes().to_vec()),
+            _ => None,
+        }.ok_or_else(|| GcpError::GitHubApi {
+            status: 0,
+            message: format!("Blob {} could not be decoded", sha),
+            documentation_url: None,
+        })?;
+
+        if let Some(cache) = self.cache() {
+            if let Err(e) = cache.put_blob(sha, &content) {
+                warn!("Failed to cache blob {}: {}", sha, e);
+            }
+        }
+        Ok(content)
+    }
+
+    /// Get the content of a file from the cache
+    ///
+    /// The blob SHA is taken from `sha` when known, otherwise from cached listings.
+    pub fn get_cached_file(&self, owner: &str, repo: &str, path: &str, ref_: Option<&str>, sha: Option<&str>) -> Result<Vec<u8>> {
+        let ref_ = ref_.unwrap_or("main");
+        let not_cached = || GcpError::NotCached {
+            item: format!("{}/{}/{}@{}", owner, repo, path, ref_),
+        };
+
+        let cache = self.cache().ok_or_else(not_cached)?;
+        let sha = match sha {
+            Some(sha) => sha.to_string(),
+            None => cache.get_file_entry(owner, repo, ref_, path)?
+                .ok_or_else(not_cached)?
+                .sha,
+        };
+
+        cache.get_blob(&sha)?.ok_or_else(not_cached)
+    }
+
+    /// Record downloaded file content in the blob cache
+    ///
+    /// The file's metadata entry is recorded as well, so the path can later be
+    /// resolved offline even when it was not fetched as part of a folder listing.
+    pub fn cache_file(&self, owner: &str, repo: &str, path: &str, ref_: Option<&str>, content: &[u8]) {
+        let cache = match self.cache() {
+            Some(cache) => cache,
+            None => return,
+        };
+        let ref_ = ref_.unwrap_or("main");
+        let sha = crate::filesystem::git_blob_sha(content);
+
+        let entry = GitHubFile {
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            path: path.to_string(),
+            sha: sha.clone(),
+            size: content.len() as i64,
+            url: String::new(),
+            html_url: String::new(),
+            git_url: String::new(),
+            download_url: None,
+            file_type: "file".to_string(),
+            content: None,
+            encoding: None,
+        };
+
+        let result = cache.put_blob(&sha, content)
+            .and_then(|_| cache.put_listing(owner, repo, ref_, path, &[entry]));
+        if let Err(e) = result {
+            warn!("Failed to cache {}: {}", path, e);
+        }
     }
 
     pub async fn download_file_content(&self, url: &str) -> Result<Vec<u8>> {
+        if self.is_offline() {
+            return Err(GcpError::NotCached { item: url.to_string() });
+        }
+
         let response = reqwest::get(url).await
             .map_err(|e| GcpError::Network { source: e })?;
 
+        let status = response.status().as_u16();
+        if let Some(reset_time) = crate::error::rate_limit_reset(status, response.headers(), chrono::Utc::now()) {
+            return Err(GcpError::RateLimit { reset_time });
+        }
         if !response.status().is_success() {
             return Err(GcpError::DownloadFailed {
                 file: url.to_string(),
@@ -87,17 +662,14 @@ impl GitHubClient {
 
     // Simplified content getter for MVP
     pub async fn get_file_info(&self, owner: &str, repo: &str, path: &str, ref_: Option<&str>) -> Result<(String, u64)> {
-        let handler = self.client.repos(owner, repo);
-        let content = handler
-            .get_content()
-            .path(path)
-            .r#ref(ref_.unwrap_or("main"))
-            .send()
-            .await
-            .map_err(|e| GcpError::GitHubApi {
-                status: 0,
-                message: format!("Failed to get file info: {}", e),
-            })?;
+        if self.is_offline() {
+            let content = self.get_cached_file(owner, repo, path, ref_, None)?;
+            let size = content.len() as u64;
+            return Ok((String::from_utf8_lossy(&content).to_string(), size));
+        }
+
+        let route = format!("/repos/{}/{}/contents/{}?{}", owner, repo, path, query_string(&[("ref", ref_.unwrap_or("main"))]));
+        let content: octocrab::models::repos::ContentItems = self.api_request(&route, None, "Failed to get file info").await?;
 
         // Handle ContentItems which contains a vector of items
         if let Some(item) = content.items.first() {
@@ -108,13 +680,156 @@ impl GitHubClient {
                 Err(GcpError::GitHubApi {
                     status: 0,
                     message: "File content could not be decoded".to_string(),
+                    documentation_url: None,
                 })
             }
         } else {
             Err(GcpError::GitHubApi {
                 status: 0,
                 message: "No file content found".to_string(),
+                documentation_url: None,
             })
         }
     }
-}
\ No newline at end of file
+}
+
+/// Describe a request that failed before GitHub answered, or whose answer couldn't be read
+fn transport_error(error: octocrab::Error, context: &str) -> GcpError {
+    // octocrab's own Display appends a backtrace; the underlying error reads better
+    let cause = std::error::Error::source(&error)
+        .map(|source| source.to_string())
+        .unwrap_or_else(|| error.to_string());
+    GcpError::GitHubApi {
+        status: 0,
+        message: format!("{}: {}", context, cause),
+        documentation_url: None,
+    }
+}
+
+/// URL-encode query parameters, e.g. `ref=feature%2Fx`
+fn query_string(pairs: &[(&str, &str)]) -> String {
+    url::form_urlencoded::Serializer::new(String::new()).extend_pairs(pairs).finish()
+}
+
+/// The parts of a commit gcp reports
+fn commit_info(commit: octocrab::models::repos::RepoCommit) -> CommitInfo {
+    let author = commit.commit.author;
+    CommitInfo {
+        sha: commit.sha,
+        date: author.as_ref().and_then(|author| author.date),
+        author: author.map(|author| author.name),
+        committer_date: commit.commit.committer.and_then(|committer| committer.date),
+        message: commit.commit.message,
+    }
+}
+
+/// A GraphQL query for the last commit touching each of `paths`, aliased `f0`, `f1`, ...
+fn history_query(paths: &[String]) -> String {
+    let mut fields = String::new();
+    for (index, path) in paths.iter().enumerate() {
+        // JSON string literals are valid GraphQL string literals
+        let path = serde_json::Value::String(path.clone());
+        fields.push_str(&format!("f{}: history(first: 1, path: {}) {{ nodes {{ committedDate }} }} ", index, path));
+    }
+    format!(
+        "query($owner: String!, $name: String!, $ref: String!) {{ repository(owner: $owner, name: $name) {{ object(expression: $ref) {{ ... on Commit {{ {}}} }} }} }}",
+        fields
+    )
+}
+
+/// The GraphQL endpoint next to a REST API URL
+///
+/// GitHub Enterprise Server serves REST under `/api/v3` and GraphQL under `/api/graphql`.
+fn graphql_url(api_url: &str) -> String {
+    let api_url = api_url.trim_end_matches('/');
+    match api_url.strip_suffix("/api/v3") {
+        Some(host) => format!("{}/api/graphql", host),
+        None => format!("{}/graphql", api_url),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_query_aliases_and_escapes_paths() {
+        let query = history_query(&["docs/a.md".to_string(), "say \"hi\".txt".to_string()]);
+        assert!(query.contains(r#"f0: history(first: 1, path: "docs/a.md")"#));
+        assert!(query.contains(r#"f1: history(first: 1, path: "say \"hi\".txt")"#));
+        assert!(query.starts_with("query($owner: String!, $name: String!, $ref: String!)"));
+    }
+
+    /// A client for an API that answers one request with `status`, `headers` and a JSON `body`
+    fn client_for(status: &str, headers: &str, body: &str) -> GitHubClient {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let api_url = format!("http://{}", listener.local_addr().unwrap());
+        let response = format!(
+            "HTTP/1.1 {}\r\n{}content-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        );
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        let mut config = crate::Config::default();
+        config.github.api_url = api_url;
+        config.cache.enabled = false;
+        let client = GitHubClient::build_octocrab(&config, None).unwrap();
+        GitHubClient {
+            client: Arc::new(RwLock::new(Arc::new(client))),
+            config: Arc::new(config),
+            cache: None,
+            app: None,
+            auth: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_read_from_response_headers() {
+        let client = client_for("403 Forbidden", "retry-after: 30\r\n", r#"{"message": "You have exceeded a rate limit"}"#);
+        let before = chrono::Utc::now();
+        match client.get_repository_info("owner", "repo").await {
+            Err(GcpError::RateLimit { reset_time }) => {
+                assert!(reset_time >= before + chrono::Duration::seconds(30));
+                assert!(reset_time <= chrono::Utc::now() + chrono::Duration::seconds(30));
+            }
+            other => panic!("expected a rate limit, got {:?}", other.map(|info| info.full_name)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_error_keeps_status_and_message() {
+        let client = client_for("404 Not Found", "", r#"{"message": "Not Found"}"#);
+        let error = client.get_blob("owner", "repo", "abc").await.unwrap_err();
+        assert!(error.is_not_found());
+        assert!(error.to_string().contains("Failed to get blob: Not Found"));
+    }
+
+    #[tokio::test]
+    async fn test_history_rate_limit_is_returned() {
+        let client = client_for(
+            "403 Forbidden",
+            "x-ratelimit-remaining: 0\r\nx-ratelimit-reset: 4102444800\r\n",
+            r#"{"message": "API rate limit exceeded"}"#,
+        );
+        let paths = vec!["docs/a.md".to_string()];
+        let error = client.last_commit_dates("owner", "repo", &paths, Some("main")).await.unwrap_err();
+        assert!(matches!(error, GcpError::RateLimit { .. }));
+        assert!(error.is_access_error());
+    }
+
+    #[test]
+    fn test_graphql_url() {
+        assert_eq!(graphql_url("https://api.github.com"), "https://api.github.com/graphql");
+        assert_eq!(graphql_url("https://ghe.example.com/api/v3/"), "https://ghe.example.com/api/graphql");
+    }
+}
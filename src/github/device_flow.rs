@@ -0,0 +1,174 @@
+use std::time::Duration;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::{GcpError, Result};
+
+/// Codes returned when starting the OAuth device flow
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub expires_in: u64,
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+/// Access token granted at the end of the device flow
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceToken {
+    pub access_token: String,
+    pub scopes: Vec<String>,
+}
+
+/// Outcome of a single poll of the token endpoint
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollOutcome {
+    Pending,
+    SlowDown,
+    Granted(DeviceToken),
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    scope: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// GitHub's OAuth device authorization flow against a web host
+pub struct DeviceFlow {
+    web_url: String,
+    client_id: String,
+    http: reqwest::Client,
+}
+
+impl DeviceFlow {
+    /// `web_url` is the web host's base URL, e.g. `https://github.com`
+    pub fn new(web_url: &str, client_id: &str) -> Self {
+        Self {
+            web_url: web_url.trim_end_matches('/').to_string(),
+            client_id: client_id.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Start the flow, returning the code the user has to enter
+    pub async fn request_code(&self, scopes: &[String]) -> Result<DeviceCode> {
+        let url = format!("{}/login/device/code", self.web_url);
+        debug!("Requesting device code from {}", url);
+
+        let response = self.http
+            .post(&url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[("client_id", self.client_id.as_str()), ("scope", &scopes.join(" "))])
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(GcpError::Authentication {
+                reason: format!("Device code request failed (HTTP {})", response.status().as_u16()),
+            });
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Poll the token endpoint once
+    pub async fn poll_once(&self, code: &DeviceCode) -> Result<PollOutcome> {
+        let url = format!("{}/login/oauth/access_token", self.web_url);
+        let response: TokenResponse = self.http
+            .post(&url)
+            .header(reqwest::header::ACCEPT, "application/json")
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("device_code", code.device_code.as_str()),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ])
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        interpret_token_response(response)
+    }
+
+    /// Poll until the user authorizes the device, the code expires, or access is denied
+    pub async fn poll(&self, code: &DeviceCode) -> Result<DeviceToken> {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(code.expires_in);
+        let mut interval = code.interval.max(1);
+
+        loop {
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            if tokio::time::Instant::now() >= deadline {
+                return Err(GcpError::Authentication {
+                    reason: "Device code expired before authorization completed".to_string(),
+                });
+            }
+
+            match self.poll_once(code).await? {
+                PollOutcome::Pending => debug!("Authorization pending"),
+                PollOutcome::SlowDown => {
+                    // GitHub asks for five extra seconds per slow_down
+                    interval += 5;
+                    debug!("Slowing down polling to every {}s", interval);
+                }
+                PollOutcome::Granted(token) => return Ok(token),
+            }
+        }
+    }
+}
+
+fn interpret_token_response(response: TokenResponse) -> Result<PollOutcome> {
+    if let Some(token) = response.access_token {
+        let scopes = response.scope
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        return Ok(PollOutcome::Granted(DeviceToken { access_token: token, scopes }));
+    }
+
+    match response.error.as_deref() {
+        Some("authorization_pending") => Ok(PollOutcome::Pending),
+        Some("slow_down") => Ok(PollOutcome::SlowDown),
+        Some(error) => Err(GcpError::Authentication {
+            reason: response.error_description.unwrap_or_else(|| error.to_string()),
+        }),
+        None => Err(GcpError::Authentication {
+            reason: "Token endpoint returned neither a token nor an error".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<PollOutcome> {
+        interpret_token_response(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_interpret_token_response() {
+        assert_eq!(parse(r#"{"error":"authorization_pending"}"#).unwrap(), PollOutcome::Pending);
+        assert_eq!(parse(r#"{"error":"slow_down","interval":10}"#).unwrap(), PollOutcome::SlowDown);
+        assert_eq!(
+            parse(r#"{"access_token":"gho_x","token_type":"bearer","scope":"repo,read:org"}"#).unwrap(),
+            PollOutcome::Granted(DeviceToken {
+                access_token: "gho_x".to_string(),
+                scopes: vec!["repo".to_string(), "read:org".to_string()],
+            })
+        );
+
+        let err = parse(r#"{"error":"access_denied","error_description":"The user denied the request"}"#).unwrap_err();
+        assert!(err.to_string().contains("denied"));
+    }
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::error::{GcpError, Result};
+
+/// A host entry in the GitHub CLI's `hosts.yml`
+#[derive(Debug, Clone, Default, Deserialize)]
+struct GhHost {
+    user: Option<String>,
+    oauth_token: Option<String>,
+    #[serde(default)]
+    users: BTreeMap<String, Option<GhUser>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct GhUser {
+    oauth_token: Option<String>,
+}
+
+/// Directory holding the gh CLI configuration, following gh's own lookup order
+pub fn gh_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("GH_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("gh"));
+    }
+    #[cfg(windows)]
+    {
+        if let Some(dir) = env::var_os("AppData") {
+            return Some(PathBuf::from(dir).join("GitHub CLI"));
+        }
+    }
+    dirs::home_dir().map(|home| home.join(".config").join("gh"))
+}
+
+/// Path of gh's `hosts.yml`, if a gh config directory can be determined
+pub fn hosts_file() -> Option<PathBuf> {
+    gh_config_dir().map(|dir| dir.join("hosts.yml"))
+}
+
+/// Read the token stored for `host` in a gh `hosts.yml` file
+///
+/// Returns `Ok(None)` when the file does not exist or has no plain-text token for
+/// the host (recent gh versions keep tokens in the system keyring instead).
+pub fn token_from_hosts_file(path: &Path, host: &str) -> Result<Option<String>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(GcpError::FileIo { path: path.to_path_buf(), source: e }),
+    };
+
+    let token = parse_hosts(&content, host).map_err(|e| GcpError::Config {
+        message: format!("Failed to parse {}: {}", path.display(), e),
+    })?;
+
+    if token.is_none() {
+        debug!("No plain-text token for {} in {}", host, path.display());
+    }
+    Ok(token)
+}
+
+fn parse_hosts(content: &str, host: &str) -> std::result::Result<Option<String>, serde_yaml::Error> {
+    // An empty file deserializes to null rather than an empty map
+    let hosts: Option<BTreeMap<String, GhHost>> = serde_yaml::from_str(content)?;
+    let entry = match hosts.and_then(|mut hosts| hosts.remove(host)) {
+        Some(entry) => entry,
+        None => return Ok(None),
+    };
+
+    if let Some(token) = entry.oauth_token.filter(|t| !t.is_empty()) {
+        return Ok(Some(token));
+    }
+
+    // Multi-account layout: tokens live under users.<login>
+    let active_user = entry.user.and_then(|user| entry.users.get(&user).cloned().flatten());
+    Ok(active_user
+        .and_then(|user| user.oauth_token)
+        .filter(|t| !t.is_empty()))
+}
+
+/// Name of the gh environment variable that carries a token for `host`
+///
+/// gh uses `GH_TOKEN` for github.com and GHE.com tenants, and
+/// `GH_ENTERPRISE_TOKEN` for GitHub Enterprise Server hosts.
+pub fn token_env_var(host: &str) -> &'static str {
+    if host == crate::github::DEFAULT_HOST || host.ends_with(".ghe.com") {
+        "GH_TOKEN"
+    } else {
+        "GH_ENTERPRISE_TOKEN"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hosts_layouts() {
+        let legacy = "github.com:\n    user: octocat\n    oauth_token: gho_legacy\n    git_protocol: https\n";
+        assert_eq!(parse_hosts(legacy, "github.com").unwrap().as_deref(), Some("gho_legacy"));
+        assert_eq!(parse_hosts(legacy, "ghe.corp").unwrap(), None);
+
+        let multi = "ghe.corp:\n    user: octocat\n    users:\n        octocat:\n            oauth_token: gho_multi\n";
+        assert_eq!(parse_hosts(multi, "ghe.corp").unwrap().as_deref(), Some("gho_multi"));
+
+        let keyring = "github.com:\n    user: octocat\n    users:\n        octocat:\n";
+        assert_eq!(parse_hosts(keyring, "github.com").unwrap(), None);
+
+        assert_eq!(parse_hosts("", "github.com").unwrap(), None);
+        assert!(parse_hosts("github.com: [unclosed", "github.com").is_err());
+    }
+}
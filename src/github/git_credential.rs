@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+use crate::error::{GcpError, Result};
+
+/// A credential obtained from (or handed back to) `git credential`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitCredential {
+    pub host: String,
+    pub username: Option<String>,
+    pub password: String,
+}
+
+impl GitCredential {
+    /// Ask git's configured credential helpers for an HTTPS credential for `host`
+    ///
+    /// Terminal prompts are disabled, so this returns `Ok(None)` instead of blocking
+    /// when no helper has a stored credential (or git is not installed).
+    pub fn fill(host: &str) -> Result<Option<Self>> {
+        let output = match run("fill", &request(host, None, None)) {
+            Ok(output) => output,
+            Err(e) => {
+                debug!("git credential fill unavailable: {}", e);
+                return Ok(None);
+            }
+        };
+
+        let fields = parse_fields(&output);
+        match fields.get("password").filter(|p| !p.is_empty()) {
+            Some(password) => Ok(Some(GitCredential {
+                host: host.to_string(),
+                username: fields.get("username").cloned(),
+                password: password.clone(),
+            })),
+            None => {
+                debug!("git credential fill returned no password for {}", host);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Tell the credential helpers the credential worked, so they can store it
+    pub fn approve(&self) -> Result<()> {
+        let input = request(&self.host, self.username.as_deref(), Some(&self.password));
+        run("approve", &input).map(|_| ()).map_err(|e| GcpError::Config {
+            message: format!("git credential approve failed: {}", e),
+        })
+    }
+}
+
+fn request(host: &str, username: Option<&str>, password: Option<&str>) -> String {
+    let mut input = format!("protocol=https\nhost={}\n", host);
+    if let Some(username) = username {
+        input.push_str(&format!("username={}\n", username));
+    }
+    if let Some(password) = password {
+        input.push_str(&format!("password={}\n", password));
+    }
+    input.push('\n');
+    input
+}
+
+fn run(action: &str, input: &str) -> std::io::Result<String> {
+    let mut child = Command::new("git")
+        .args(["credential", action])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "git credential {} exited with {}: {}",
+            action,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Parse git's `key=value` credential format
+fn parse_fields(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_and_parse() {
+        assert_eq!(request("ghe.corp", None, None), "protocol=https\nhost=ghe.corp\n\n");
+
+        let fields = parse_fields("protocol=https\nhost=github.com\nusername=octocat\npassword=gho_a=b\n");
+        assert_eq!(fields.get("username").map(String::as_str), Some("octocat"));
+        assert_eq!(fields.get("password").map(String::as_str), Some("gho_a=b"));
+    }
+}
@@ -1,15 +1,34 @@
+pub mod app;
 pub mod auth;
 pub mod client;
+pub mod device_flow;
+pub mod gh_cli;
+pub mod git_credential;
+pub mod netrc;
 pub mod types;
 
+pub use app::GitHubApp;
 pub use auth::{Authentication, AuthSource};
 pub use client::GitHubClient;
-pub use types::{GitHubFile, RepositoryInfo, GitHubFileContent, GitHubRateLimitResponse};
+pub use types::{CommitInfo, GitHubFile, GitTree, GitTreeEntry, RepositoryInfo, GitHubFileContent, GitHubRateLimitResponse};
 
 use crate::error::{GcpError, Result};
 
+/// Host of the public GitHub instance
+pub const DEFAULT_HOST: &str = "github.com";
+
+/// The bare host of `host`, which may be given as a URL such as `https://ghe.corp/`
+pub fn normalize_host(host: &str) -> &str {
+    let host = host.trim_end_matches('/');
+    host.strip_prefix("https://")
+        .or_else(|| host.strip_prefix("http://"))
+        .unwrap_or(host)
+}
+
 #[derive(Debug, Clone)]
 pub struct GitHubUrl {
+    /// Web host the URL points at (github.com or a GitHub Enterprise Server host)
+    pub host: String,
     pub owner: String,
     pub repo: String,
     pub path: Option<String>,
@@ -25,28 +44,54 @@ pub enum UrlType {
 }
 
 impl GitHubUrl {
+    /// Parse a github.com or raw.githubusercontent.com URL
     pub fn parse(url: &str) -> Result<Self> {
+        Self::parse_with_host(url, DEFAULT_HOST)
+    }
+
+    /// Parse a URL, additionally accepting the given GitHub Enterprise Server host
+    ///
+    /// GHE URLs look like `https://ghe.corp/owner/repo/tree/ref/path`; raw file URLs
+    /// are either `https://ghe.corp/raw/owner/repo/ref/path` or, with subdomain
+    /// isolation, `https://raw.ghe.corp/owner/repo/ref/path`.
+    pub fn parse_with_host(url: &str, host: &str) -> Result<Self> {
+        let host = normalize_host(host);
         let parsed_url = url::Url::parse(url)?;
 
+        let authority = match (parsed_url.host_str(), parsed_url.port()) {
+            (Some(name), Some(port)) => format!("{}:{}", name, port),
+            (Some(name), None) => name.to_string(),
+            (None, _) => return Err(GcpError::InvalidUrl { url: url.to_string() }),
+        };
+
         // Handle different URL formats
-        if parsed_url.host_str() == Some("raw.githubusercontent.com") {
+        if authority == "raw.githubusercontent.com" {
             // Raw URL format: https://raw.githubusercontent.com/owner/repo/ref/path
-            Self::parse_raw_url(&parsed_url)
-        } else if parsed_url.host_str() == Some("github.com") {
+            Self::parse_raw_url(&parsed_url, DEFAULT_HOST, 0)
+        } else if authority == DEFAULT_HOST {
             // GitHub URL format: https://github.com/owner/repo/blob/ref/path
-            Self::parse_github_url(&parsed_url)
+            Self::parse_github_url(&parsed_url, DEFAULT_HOST)
+        } else if host != DEFAULT_HOST && authority == format!("raw.{}", host) {
+            Self::parse_raw_url(&parsed_url, host, 0)
+        } else if host != DEFAULT_HOST && authority == host {
+            if parsed_url.path_segments().and_then(|mut s| s.next()) == Some("raw") {
+                Self::parse_raw_url(&parsed_url, host, 1)
+            } else {
+                Self::parse_github_url(&parsed_url, host)
+            }
         } else {
-            return Err(GcpError::InvalidUrl {
+            Err(GcpError::InvalidUrl {
                 url: url.to_string(),
-            });
+            })
         }
     }
 
-    fn parse_raw_url(parsed_url: &url::Url) -> Result<Self> {
+    fn parse_raw_url(parsed_url: &url::Url, host: &str, skip: usize) -> Result<Self> {
         let path_segments: Vec<&str> = parsed_url.path_segments()
             .ok_or_else(|| GcpError::InvalidUrl {
                 url: parsed_url.to_string(),
             })?
+            .skip(skip)
             .collect();
 
         if path_segments.len() < 3 {
@@ -67,6 +112,7 @@ impl GitHubUrl {
         let url_type = if path.is_some() { UrlType::File } else { UrlType::Repository };
 
         Ok(GitHubUrl {
+            host: host.to_string(),
             owner,
             repo,
             path,
@@ -75,7 +121,7 @@ impl GitHubUrl {
         })
     }
 
-    fn parse_github_url(parsed_url: &url::Url) -> Result<Self> {
+    fn parse_github_url(parsed_url: &url::Url, host: &str) -> Result<Self> {
         let path_segments: Vec<&str> = parsed_url.path_segments()
             .ok_or_else(|| GcpError::InvalidUrl {
                 url: parsed_url.to_string(),
@@ -110,6 +156,7 @@ impl GitHubUrl {
             };
 
             Ok(GitHubUrl {
+                host: host.to_string(),
                 owner,
                 repo,
                 path,
@@ -119,6 +166,7 @@ impl GitHubUrl {
         } else {
             // Repository root URL
             Ok(GitHubUrl {
+                host: host.to_string(),
                 owner,
                 repo,
                 path: None,
@@ -144,10 +192,22 @@ impl GitHubUrl {
         }
     }
 
+    /// Base URL of the web UI for this URL's host
+    pub fn web_url(&self) -> String {
+        format!("https://{}", self.host)
+    }
+
     pub fn raw_url(&self) -> Option<String> {
+        let raw_base = if self.host == DEFAULT_HOST {
+            "https://raw.githubusercontent.com".to_string()
+        } else {
+            format!("https://{}/raw", self.host)
+        };
+
         match self.url_type {
             UrlType::File => Some(format!(
-                "https://raw.githubusercontent.com/{}/{}/{}/{}",
+                "{}/{}/{}/{}/{}",
+                raw_base,
                 self.owner,
                 self.repo,
                 self.ref_.as_deref().unwrap_or("main"),
@@ -156,4 +216,40 @@ impl GitHubUrl {
             _ => None,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_enterprise_urls() {
+        let url = GitHubUrl::parse_with_host("https://ghe.corp/owner/repo/tree/main/src/lib", "ghe.corp").unwrap();
+        assert_eq!(url.host, "ghe.corp");
+        assert_eq!(url.url_type, UrlType::Folder);
+        assert_eq!(url.path.as_deref(), Some("src/lib"));
+
+        let url = GitHubUrl::parse_with_host("https://ghe.corp/raw/owner/repo/main/a.txt", "ghe.corp").unwrap();
+        assert_eq!((url.owner.as_str(), url.repo.as_str()), ("owner", "repo"));
+        assert_eq!(url.raw_url().unwrap(), "https://ghe.corp/raw/owner/repo/main/a.txt");
+
+        let url = GitHubUrl::parse_with_host("https://raw.ghe.corp/owner/repo/main/a.txt", "ghe.corp").unwrap();
+        assert_eq!(url.url_type, UrlType::File);
+
+        assert!(GitHubUrl::parse("https://ghe.corp/owner/repo/tree/main/src").is_err());
+
+        // --host may be given as a URL
+        for host in ["https://ghe.corp", "https://ghe.corp/", "ghe.corp/"] {
+            let url = GitHubUrl::parse_with_host("https://ghe.corp/owner/repo/tree/main/src", host).unwrap();
+            assert_eq!(url.host, "ghe.corp");
+            assert_eq!(crate::GitHubConfig::for_host(host).api_url, "https://ghe.corp/api/v3");
+        }
+    }
+
+    #[test]
+    fn test_parse_github_com_urls() {
+        let url = GitHubUrl::parse("https://github.com/owner/repo/blob/main/README.md").unwrap();
+        assert_eq!(url.host, DEFAULT_HOST);
+        assert_eq!(url.raw_url().unwrap(), "https://raw.githubusercontent.com/owner/repo/main/README.md");
+    }
+}
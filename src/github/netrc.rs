@@ -0,0 +1,205 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::error::{GcpError, Result};
+
+/// Tokens with a meaning of their own, which can't stand in for a value
+const KEYWORDS: [&str; 6] = ["machine", "default", "login", "password", "account", "macdef"];
+
+/// A `machine` entry from a netrc file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NetrcEntry {
+    pub machine: String,
+    pub login: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Parsed netrc file; only explicit `machine` entries are kept (`default` is ignored
+/// so credentials meant for other hosts are never sent to GitHub)
+#[derive(Debug, Clone, Default)]
+pub struct Netrc {
+    pub entries: Vec<NetrcEntry>,
+}
+
+impl Netrc {
+    /// Location of the netrc file: `$NETRC`, or `~/.netrc` (`~/_netrc` on Windows)
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("NETRC") {
+            return Some(PathBuf::from(path));
+        }
+        let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+        dirs::home_dir().map(|home| home.join(name))
+    }
+
+    /// Load a netrc file; a missing file yields `Ok(None)`
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(GcpError::FileIo { path: path.to_path_buf(), source: e }),
+        };
+
+        Self::parse(&content).map(Some).map_err(|reason| GcpError::Config {
+            message: format!("Failed to parse netrc file {}: {}", path.display(), reason),
+        })
+    }
+
+    pub fn parse(content: &str) -> std::result::Result<Self, String> {
+        let mut entries = Vec::new();
+        let mut current: Option<NetrcEntry> = None;
+        let mut in_default = false;
+
+        // A token starting with '#' comments out the rest of the line, so passwords
+        // may still contain '#'; macro definitions run until a blank line
+        let mut lines = content.lines();
+        while let Some(line) = lines.next() {
+            let mut tokens = line.split_whitespace();
+
+            while let Some(token) = tokens.next() {
+                if token.starts_with('#') {
+                    break;
+                }
+
+                // A keyword where the value should be means the value was left out
+                let mut value = |keyword: &str| {
+                    tokens.next()
+                        .filter(|value| !KEYWORDS.contains(value))
+                        .map(str::to_string)
+                        .ok_or_else(|| format!("'{}' is missing a value", keyword))
+                };
+
+                match token {
+                    "machine" => {
+                        entries.extend(current.take());
+                        current = Some(NetrcEntry { machine: value("machine")?, ..Default::default() });
+                        in_default = false;
+                    }
+                    "default" => {
+                        entries.extend(current.take());
+                        in_default = true;
+                    }
+                    "login" | "password" | "account" => {
+                        let v = value(token)?;
+                        match (&mut current, in_default) {
+                            (_, true) => {}
+                            (Some(entry), false) => match token {
+                                "login" => entry.login = Some(v),
+                                "password" => entry.password = Some(v),
+                                _ => {}
+                            },
+                            (None, false) => return Err(format!("'{}' appears before any machine", token)),
+                        }
+                    }
+                    "macdef" => {
+                        value("macdef")?;
+                        for body in lines.by_ref() {
+                            if body.trim().is_empty() {
+                                break;
+                            }
+                        }
+                        break;
+                    }
+                    // Other tools' extensions; their values are skipped the same way
+                    other => debug!("Ignoring unknown netrc token '{}'", other),
+                }
+            }
+        }
+        entries.extend(current.take());
+
+        Ok(Netrc { entries })
+    }
+
+    /// Find the entry for `machine` that carries a password
+    pub fn find(&self, machine: &str) -> Option<&NetrcEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.machine.eq_ignore_ascii_case(machine) && entry.password.is_some())
+    }
+}
+
+/// Machine names whose netrc entries may hold a token for `host`
+pub fn machines_for_host(host: &str) -> Vec<String> {
+    if host == crate::github::DEFAULT_HOST {
+        vec!["api.github.com".to_string(), host.to_string()]
+    } else {
+        vec![host.to_string()]
+    }
+}
+
+/// Look up a token for `host` in the default netrc file
+pub fn token_for_host(host: &str) -> Result<Option<(String, PathBuf)>> {
+    let path = match Netrc::default_path() {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let netrc = match Netrc::load(&path)? {
+        Some(netrc) => netrc,
+        None => return Ok(None),
+    };
+
+    for machine in machines_for_host(host) {
+        if let Some(password) = netrc.find(&machine).and_then(|entry| entry.password.clone()) {
+            debug!("Found netrc entry for {} in {}", machine, path.display());
+            return Ok(Some((password, path)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_netrc() {
+        let content = "\
+# CI credentials
+machine api.github.com login x-access-token password ghp_api
+machine ghe.corp
+    login bot
+    password ghp_ghe
+macdef init
+cd /pub
+
+default login anonymous password secret
+";
+        let netrc = Netrc::parse(content).unwrap();
+        assert_eq!(netrc.entries.len(), 2);
+        assert_eq!(netrc.find("api.github.com").unwrap().password.as_deref(), Some("ghp_api"));
+        assert_eq!(netrc.find("ghe.corp").unwrap().login.as_deref(), Some("bot"));
+        assert!(netrc.find("example.com").is_none());
+    }
+
+    #[test]
+    fn test_parse_netrc_comments_and_unknown_tokens() {
+        let content = "\
+machine ghe.corp login bot password p#ss # rotated yearly
+machine api.github.com port 443 login x password ghp_api
+";
+        let netrc = Netrc::parse(content).unwrap();
+        assert_eq!(netrc.find("ghe.corp").unwrap().password.as_deref(), Some("p#ss"));
+        assert_eq!(netrc.find("api.github.com").unwrap().password.as_deref(), Some("ghp_api"));
+    }
+
+    #[test]
+    fn test_parse_netrc_errors() {
+        assert!(Netrc::parse("machine").is_err());
+        assert!(Netrc::parse("login bot password x").is_err());
+        assert!(Netrc::parse("machine login bot password x").is_err());
+        assert!(Netrc::parse("machine ghe.corp login bot password").is_err());
+        assert!(Netrc::parse("machine ghe.corp password login bot").is_err());
+    }
+
+    #[test]
+    fn test_load_broken_netrc_is_config_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".netrc");
+        std::fs::write(&path, "machine api.github.com login x-access-token password\n").unwrap();
+
+        match Netrc::load(&path) {
+            Err(GcpError::Config { message }) => assert!(message.contains("'password' is missing a value")),
+            other => panic!("expected a config error, got {:?}", other.map(|netrc| netrc.map(|netrc| netrc.entries))),
+        }
+    }
+}
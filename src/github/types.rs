@@ -58,6 +58,50 @@ pub struct RepositoryInfo {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub pushed_at: DateTime<Utc>,
+    /// SPDX identifier of the detected license, or its name when GitHub has none
+    #[serde(default)]
+    pub license: Option<String>,
+}
+
+/// A tree listed by the Git Trees API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitTree {
+    pub sha: String,
+    pub tree: Vec<GitTreeEntry>,
+    /// Whether GitHub left entries out because the tree is too large
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitTreeEntry {
+    pub path: String,
+    /// Octal git file mode, e.g. `100644`, `100755` or `120000`
+    pub mode: String,
+    #[serde(rename = "type")]
+    pub entry_type: String,
+    pub sha: String,
+}
+
+impl GitTree {
+    /// Git file mode of the blob at `path`
+    pub fn mode(&self, path: &str) -> Option<u32> {
+        self.tree.iter()
+            .find(|entry| entry.path == path)
+            .and_then(|entry| u32::from_str_radix(&entry.mode, 8).ok())
+    }
+}
+
+/// A commit as summarised by `gcp info`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitInfo {
+    pub sha: String,
+    pub author: Option<String>,
+    pub date: Option<DateTime<Utc>>,
+    /// When the commit was made, which differs from `date` for rebased or applied patches
+    #[serde(default)]
+    pub committer_date: Option<DateTime<Utc>>,
+    pub message: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
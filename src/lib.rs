@@ -1,26 +1,35 @@
+pub mod cache;
 pub mod error;
 pub mod github;
 pub mod downloader;
 pub mod filesystem;
+pub mod output;
+pub mod settings;
+pub mod vendor;
 
 pub use error::{GcpError, Result};
 
 use tracing::info;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Config {
     pub github: GitHubConfig,
     pub download: DownloadConfig,
     pub filesystem: FilesystemConfig,
+    pub cache: CacheConfig,
 }
 
 #[derive(Debug, Clone)]
 pub struct GitHubConfig {
+    /// Web host, e.g. github.com or a GitHub Enterprise Server host
+    pub host: String,
     pub api_url: String,
     pub max_concurrent_requests: usize,
     pub retry_attempts: u32,
     pub rate_limit_buffer: usize,
+    /// How long a successful token validation is trusted, in seconds
+    pub auth_cache_ttl_seconds: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -28,32 +37,71 @@ pub struct DownloadConfig {
     pub chunk_size: usize,
     pub max_file_size: u64,
     pub timeout_seconds: u64,
+    /// Glob patterns a file must match to be copied (empty: everything)
+    pub include: Vec<String>,
+    /// Glob patterns of files and directories to leave out
+    pub exclude: Vec<String>,
+    /// Mirror folders: overwrite changed files, skip unchanged ones and delete
+    /// local files the source no longer has
+    pub delete: bool,
+    /// Write symlinks as copies of the files they point to
+    pub copy_symlinks: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct FilesystemConfig {
     pub default_permissions: Option<u32>,
-    pub preserve_timestamps: bool,
+    /// Set each file's modification time from commit history instead of the time it's written
+    pub preserve_timestamps: Option<TimestampSource>,
     pub create_intermediate_dirs: bool,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            github: GitHubConfig::default(),
-            download: DownloadConfig::default(),
-            filesystem: FilesystemConfig::default(),
-        }
-    }
+/// Which commit a preserved modification time comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampSource {
+    /// The last commit that touched each file (batched history queries)
+    #[default]
+    History,
+    /// The commit the ref resolves to, for every file (one request)
+    Commit,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub dir: Option<PathBuf>,
+    pub offline: bool,
 }
 
 impl Default for GitHubConfig {
     fn default() -> Self {
         Self {
+            host: github::DEFAULT_HOST.to_string(),
             api_url: "https://api.github.com".to_string(),
             max_concurrent_requests: 10,
             retry_attempts: 3,
             rate_limit_buffer: 5, // Keep 5 requests as buffer
+            auth_cache_ttl_seconds: 24 * 60 * 60,
+        }
+    }
+}
+
+impl GitHubConfig {
+    /// Default configuration targeting the given host
+    ///
+    /// GitHub Enterprise Server exposes its REST API under `/api/v3`.
+    pub fn for_host(host: &str) -> Self {
+        let host = github::normalize_host(host);
+
+        if host == github::DEFAULT_HOST {
+            return Self::default();
+        }
+
+        Self {
+            host: host.to_string(),
+            api_url: format!("https://{}/api/v3", host),
+            ..Self::default()
         }
     }
 }
@@ -64,6 +112,10 @@ impl Default for DownloadConfig {
             chunk_size: 1024 * 1024, // 1MB chunks
             max_file_size: 100 * 1024 * 1024, // 100MB max file size
             timeout_seconds: 30,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            delete: false,
+            copy_symlinks: false,
         }
     }
 }
@@ -72,12 +124,22 @@ impl Default for FilesystemConfig {
     fn default() -> Self {
         Self {
             default_permissions: Some(0o644),
-            preserve_timestamps: false,
+            preserve_timestamps: None,
             create_intermediate_dirs: true,
         }
     }
 }
 
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: None, // Falls back to get_cache_dir()
+            offline: false,
+        }
+    }
+}
+
 pub fn init_logging(verbose: bool, quiet: bool) {
     use tracing_subscriber::{fmt, EnvFilter};
 
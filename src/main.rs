@@ -1,79 +1,340 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand};
 use std::path::PathBuf;
-use tracing::{info, error, debug};
+use tracing::{info, warn, error, debug};
 
 #[derive(Parser)]
 #[command(name = "gcp")]
 #[command(about = "Copy files/folders from GitHub repositories")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
+#[command(subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// GitHub URL to copy from (file or folder)
     /// Examples:
     ///   https://github.com/owner/repo/blob/main/path/to/file.txt
     ///   https://github.com/owner/repo/tree/main/folder-name
     ///   https://raw.githubusercontent.com/owner/repo/main/file.txt
-    #[arg(value_parser = validate_github_url)]
-    source: String,
+    ///   https://ghe.example.com/owner/repo/tree/main/folder-name (with --host)
+    #[arg(value_parser = validate_github_url, required_unless_present = "from_file", conflicts_with = "from_file")]
+    source: Option<String>,
 
-    /// Local destination path
+    /// Local destination path (`-` writes a single file to stdout)
     #[arg(value_parser = validate_local_path)]
     destination: Option<PathBuf>,
 
+    /// GitHub Enterprise Server host (default: github.com)
+    #[arg(long, env = "GH_HOST", global = true)]
+    host: Option<String>,
+
     /// GitHub authentication token (or use GITHUB_TOKEN env var)
-    #[arg(long, short = 't')]
+    #[arg(long, short = 't', global = true)]
     auth_token: Option<String>,
 
+    /// GitHub App ID to authenticate as (requires --app-installation-id and --app-private-key)
+    #[arg(long, env = "GCP_APP_ID", global = true, requires_all = ["app_installation_id", "app_private_key"])]
+    app_id: Option<u64>,
+
+    /// GitHub App installation ID
+    #[arg(long, env = "GCP_APP_INSTALLATION_ID", global = true, requires = "app_id")]
+    app_installation_id: Option<u64>,
+
+    /// Path to the GitHub App's PEM private key
+    #[arg(long, env = "GCP_APP_PRIVATE_KEY", global = true, requires = "app_id")]
+    app_private_key: Option<PathBuf>,
+
     /// Enable verbose output
-    #[arg(long, short = 'v')]
+    #[arg(long, short = 'v', global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
     verbose: bool,
 
     /// Suppress non-error output
-    #[arg(long, short = 'q')]
+    #[arg(long, short = 'q', global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
     quiet: bool,
 
     /// Show what would be copied without actually copying
-    #[arg(long)]
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
     dry_run: bool,
 
     /// Show progress bar
-    #[arg(long)]
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
     progress: bool,
 
+    /// Output format: human-readable text, one JSON document, or NDJSON events
+    #[arg(long, value_enum, env = "GCP_OUTPUT", global = true)]
+    output: Option<gcp::output::OutputFormat>,
+
+    /// Copy every source listed in a file (`-` for stdin), one `<url> [destination]` per line
+    #[arg(long, value_name = "PATH")]
+    from_file: Option<PathBuf>,
+
     /// Overwrite existing files (default: auto-rename)
-    #[arg(long, short = 'f')]
+    #[arg(long, short = 'f', num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
     force: bool,
 
-    /// Preserve original file modification times
+    /// Make a folder destination match the source: skip unchanged files and
+    /// delete local files the source doesn't have
     #[arg(long)]
-    preserve_timestamps: bool,
+    delete: bool,
+
+    /// Write the source into a .tar, .tar.gz/.tgz or .zip archive instead of a directory
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["destination", "delete", "from_file"])]
+    archive: Option<PathBuf>,
+
+    /// Set modification times from the last commit touching each file (`history`),
+    /// or from the copied commit for every file (`commit`, one request)
+    #[arg(long, value_enum, value_name = "SOURCE", num_args = 0..=1, require_equals = true, default_missing_value = "history")]
+    preserve_timestamps: Option<gcp::TimestampSource>,
+
+    /// Write symlinks as copies of the files they point to instead of as links
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
+    copy_symlinks: bool,
+
+    /// Octal mode for files that aren't executable in git (default: 0644)
+    #[arg(long, value_name = "MODE", value_parser = gcp::settings::parse_mode)]
+    default_permissions: Option<u32>,
 
     /// Exclude files matching pattern (glob)
-    #[arg(long)]
+    #[arg(long, global = true)]
     exclude: Vec<String>,
 
     /// Include only files matching pattern (glob)
-    #[arg(long)]
+    #[arg(long, global = true)]
     include: Vec<String>,
 
     /// Maximum concurrent downloads (default: 10)
-    #[arg(long, default_value = "10")]
-    max_concurrent: usize,
+    #[arg(long, env = "GCP_MAX_CONCURRENT")]
+    max_concurrent: Option<usize>,
 
     /// Request timeout in seconds (default: 30)
-    #[arg(long, default_value = "30")]
-    timeout: u64,
+    #[arg(long, env = "GCP_TIMEOUT")]
+    timeout: Option<u64>,
 
     /// Maximum retry attempts (default: 3)
-    #[arg(long, default_value = "3")]
-    retry: u32,
+    #[arg(long, env = "GCP_RETRY")]
+    retry: Option<u32>,
 
     /// Cache directory for metadata
-    #[arg(long)]
+    #[arg(long, env = "GCP_CACHE_DIR", global = true)]
     cache_dir: Option<PathBuf>,
 
     /// Disable caching
-    #[arg(long)]
+    #[arg(long, global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
     no_cache: bool,
+
+    /// Serve listings and downloads from the local cache only (no network access)
+    #[arg(long, conflicts_with = "no_cache", global = true, num_args = 0..=1, require_equals = true, default_missing_value = "true", default_value_t = false, action = ArgAction::Set)]
+    offline: bool,
+
+    /// How long a successful token validation is reused, in seconds (default: 86400)
+    #[arg(long, env = "GCP_AUTH_CACHE_TTL", global = true)]
+    auth_cache_ttl: Option<u64>,
+
+    /// Config file to read defaults from (default: <config dir>/gcp/config.toml)
+    #[arg(long, env = "GCP_CONFIG", global = true)]
+    config: Option<PathBuf>,
+
+    /// Named profile from the config file to apply
+    #[arg(long, env = "GCP_PROFILE", global = true)]
+    profile: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage GitHub credentials
+    #[command(subcommand)]
+    Auth(AuthCommand),
+    /// List the contents of a repository path
+    Ls {
+        /// GitHub URL of a folder, file or repository
+        #[arg(value_parser = validate_github_url)]
+        url: String,
+
+        /// Long format: type, size and blob SHA
+        #[arg(long, short = 'l')]
+        long: bool,
+
+        /// List subdirectories recursively
+        #[arg(long, short = 'R')]
+        recursive: bool,
+
+        /// Show a tree of everything below the path (implies --recursive)
+        #[arg(long)]
+        tree: bool,
+    },
+    /// Show repository, ref and path metadata
+    Info {
+        /// GitHub URL of a repository, folder or file
+        #[arg(value_parser = validate_github_url)]
+        url: String,
+    },
+    /// Compare a remote folder with a local directory
+    Diff {
+        /// GitHub URL of a folder
+        #[arg(value_parser = validate_github_url)]
+        url: String,
+
+        /// Local directory to compare against
+        #[arg(value_parser = validate_local_path)]
+        local_dir: PathBuf,
+
+        /// Show unified diffs of modified files
+        #[arg(long, short = 'p')]
+        patch: bool,
+    },
+    /// Copy every source in gcp.toml as pinned by gcp.lock, locking new sources first
+    Vendor {
+        /// Manifest to read; gcp.lock is kept next to it
+        #[arg(long, default_value = gcp::vendor::MANIFEST_FILE)]
+        manifest: PathBuf,
+    },
+    /// Re-resolve every source's ref and rewrite gcp.lock
+    Update {
+        /// Manifest to read; gcp.lock is kept next to it
+        #[arg(long, default_value = gcp::vendor::MANIFEST_FILE)]
+        manifest: PathBuf,
+    },
+    /// Verify that the vendored files match gcp.lock, without network access
+    Check {
+        /// Manifest to read; gcp.lock is kept next to it
+        #[arg(long, default_value = gcp::vendor::MANIFEST_FILE)]
+        manifest: PathBuf,
+    },
+    /// Write a remote file to stdout
+    Cat {
+        /// GitHub URL of a file
+        #[arg(value_parser = validate_github_url)]
+        url: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthCommand {
+    /// Log in with GitHub's OAuth device flow and store the token
+    Login {
+        /// OAuth app client ID (or hosts.<host>.oauth-client-id in the config file)
+        #[arg(long, env = "GCP_OAUTH_CLIENT_ID")]
+        client_id: Option<String>,
+
+        /// Scopes to request
+        #[arg(long, value_delimiter = ',', default_value = "repo")]
+        scopes: Vec<String>,
+    },
+    /// Show which credentials would be used, with their scopes and expiry
+    Status,
+    /// Validate the token against GitHub now instead of on the first 401/403
+    Check {
+        /// Ignore any cached validation result
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Remove the token stored by `gcp auth login`
+    Logout,
+}
+
+impl Cli {
+    /// Fill in anything not given on the command line or environment from the config file
+    ///
+    /// Switches given on the command line win either way (`--force=false` turns off
+    /// a configured `force = true`), and drop a configured switch they conflict with.
+    fn apply_defaults(&mut self, defaults: gcp::settings::Defaults, matches: &ArgMatches) {
+        let given = |id: &str| matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable));
+        let layer = |id: &str, value: bool, default: Option<bool>| if given(id) { value } else { default.unwrap_or(value) };
+
+        // Normalized once, so the API config, credentials and URL parsing all see the same host
+        self.host = self.host.take().or(defaults.host).map(|host| gcp::github::normalize_host(&host).to_string());
+        self.verbose = layer("verbose", self.verbose, defaults.verbose);
+        self.quiet = layer("quiet", self.quiet, defaults.quiet);
+        self.dry_run = layer("dry_run", self.dry_run, defaults.dry_run);
+        self.progress = layer("progress", self.progress, defaults.progress);
+        self.force = layer("force", self.force, defaults.force);
+        if defaults.preserve_timestamps == Some(true) {
+            self.preserve_timestamps = self.preserve_timestamps.or(Some(gcp::TimestampSource::History));
+        }
+        self.copy_symlinks = layer("copy_symlinks", self.copy_symlinks, defaults.copy_symlinks);
+        self.default_permissions = self.default_permissions.or(defaults.default_permissions);
+        if self.exclude.is_empty() {
+            self.exclude = defaults.exclude.unwrap_or_default();
+        }
+        if self.include.is_empty() {
+            self.include = defaults.include.unwrap_or_default();
+        }
+        self.max_concurrent = self.max_concurrent.or(defaults.max_concurrent);
+        self.timeout = self.timeout.or(defaults.timeout);
+        self.retry = self.retry.or(defaults.retry);
+        self.cache_dir = self.cache_dir.take().or(defaults.cache_dir);
+        self.no_cache = layer("no_cache", self.no_cache, defaults.no_cache);
+        self.offline = layer("offline", self.offline, defaults.offline);
+        self.auth_cache_ttl = self.auth_cache_ttl.or(defaults.auth_cache_ttl);
+        self.output = self.output.or(defaults.output);
+
+        // Conflicts between two command-line switches are rejected by clap
+        if self.verbose && self.quiet {
+            if given("verbose") {
+                self.quiet = false;
+            } else if given("quiet") {
+                self.verbose = false;
+            }
+        }
+        if self.offline && self.no_cache {
+            if given("offline") {
+                self.no_cache = false;
+            } else if given("no_cache") {
+                self.offline = false;
+            }
+        }
+    }
+
+    /// Build the library configuration from the (already layered) arguments
+    fn build_config(&self, github: gcp::GitHubConfig) -> gcp::Config {
+        let download = gcp::DownloadConfig::default();
+        gcp::Config {
+            github: gcp::GitHubConfig {
+                max_concurrent_requests: self.max_concurrent.unwrap_or(github.max_concurrent_requests),
+                retry_attempts: self.retry.unwrap_or(github.retry_attempts),
+                auth_cache_ttl_seconds: self.auth_cache_ttl.unwrap_or(github.auth_cache_ttl_seconds),
+                ..github
+            },
+            download: gcp::DownloadConfig {
+                timeout_seconds: self.timeout.unwrap_or(download.timeout_seconds),
+                include: self.include.clone(),
+                exclude: self.exclude.clone(),
+                delete: self.delete,
+                copy_symlinks: self.copy_symlinks,
+                ..download
+            },
+            filesystem: gcp::FilesystemConfig {
+                default_permissions: Some(self.default_permissions.unwrap_or(0o644)),
+                preserve_timestamps: self.preserve_timestamps,
+                create_intermediate_dirs: true,
+            },
+            cache: gcp::CacheConfig {
+                enabled: !self.no_cache,
+                dir: self.cache_dir.clone(),
+                offline: self.offline,
+            },
+        }
+    }
+}
+
+/// GitHub settings for `host`, honouring an `api-url` override from the config file
+fn github_config_for(host: &str, config_file: &gcp::settings::ConfigFile) -> gcp::GitHubConfig {
+    let mut github = gcp::GitHubConfig::for_host(host);
+    if let Some(api_url) = config_file.host(host).and_then(|h| h.api_url.clone()) {
+        github.api_url = api_url;
+    }
+    github
+}
+
+fn load_config_file(path: Option<&std::path::Path>) -> gcp::Result<gcp::settings::ConfigFile> {
+    match path {
+        Some(path) if !path.exists() => Err(gcp::GcpError::Config {
+            message: format!("Config file not found: {}", path.display()),
+        }),
+        Some(path) => gcp::settings::ConfigFile::load(path),
+        None => gcp::settings::ConfigFile::load_default(),
+    }
 }
 
 fn validate_github_url(url: &str) -> Result<String, String> {
@@ -82,8 +343,9 @@ fn validate_github_url(url: &str) -> Result<String, String> {
         return Err("URL cannot be empty".to_string());
     }
 
-    if !url.starts_with("https://github.com/") && !url.starts_with("https://raw.githubusercontent.com/") {
-        return Err("URL must start with https://github.com/ or https://raw.githubusercontent.com/".to_string());
+    // The host itself is checked against --host in GitHubUrl::parse_with_host
+    if !url.starts_with("https://") {
+        return Err("URL must start with https://".to_string());
     }
 
     Ok(url.to_string())
@@ -112,7 +374,22 @@ fn validate_local_path(path: &str) -> Result<PathBuf, String> {
 
 #[tokio::main]
 async fn main() {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    // Layer config file defaults under CLI arguments and environment variables
+    let config_file = match load_config_file(cli.config.as_deref())
+        .and_then(|file| file.resolve(cli.profile.as_deref()).map(|defaults| (file, defaults)))
+    {
+        Ok((file, defaults)) => {
+            cli.apply_defaults(defaults, &matches);
+            file
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(e.exit_code());
+        }
+    };
 
     // Initialize logging
     let log_level = if cli.verbose {
@@ -126,78 +403,127 @@ async fn main() {
     tracing_subscriber::fmt()
         .with_max_level(log_level)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .init();
 
-    // Validate input arguments
-    let destination = cli.destination.unwrap_or_else(|| PathBuf::from("."));
+    // `gcp <url> -` is shorthand for `gcp cat <url>`
+    let command = cli.command.take().or_else(|| match (&cli.source, &cli.destination) {
+        (Some(url), Some(destination)) if destination.as_os_str() == "-" => Some(Command::Cat { url: url.clone() }),
+        _ => None,
+    });
 
-    info!("Starting GitHub Copy Tool");
-    info!("Source: {}", cli.source);
-    info!("Destination: {}", destination.display());
-
-    // Parse and validate the GitHub URL
-    let github_url = match gcp::github::GitHubUrl::parse(&cli.source) {
-        Ok(url) => {
-            debug!("Parsed GitHub URL: {:?}", url);
-            url
-        }
-        Err(e) => {
-            error!("Failed to parse GitHub URL: {}", e);
-            std::process::exit(1);
+    if let Some(command) = command {
+        let host = cli.host.clone().unwrap_or_else(|| gcp::github::DEFAULT_HOST.to_string());
+        let result = match command {
+            Command::Auth(command) => {
+                let config = cli.build_config(github_config_for(&host, &config_file));
+                run_auth(command, &host, cli.auth_token.clone(), &config_file, config).await
+            }
+            Command::Ls { url, long, recursive, tree } => {
+                run_ls(&mut cli, &config_file, &url, long, recursive || tree, tree).await
+            }
+            Command::Info { url } => run_info(&mut cli, &config_file, &url).await,
+            Command::Diff { url, local_dir, patch } => run_diff(&mut cli, &config_file, &url, &local_dir, patch).await,
+            Command::Cat { url } => run_cat(&mut cli, &config_file, &url).await,
+            Command::Vendor { manifest } => run_vendor(&mut cli, &config_file, &manifest, false).await,
+            Command::Update { manifest } => run_vendor(&mut cli, &config_file, &manifest, true).await,
+            Command::Check { manifest } => run_check(&cli, &manifest),
+        };
+        if let Err(e) = result {
+            if cli.output.unwrap_or_default().is_machine_readable() {
+                print_or_exit(&gcp::output::ErrorDocument { error: (&e).into() });
+            } else {
+                error!("{}", e);
+            }
+            std::process::exit(e.exit_code());
         }
-    };
+        return;
+    }
 
-    // Handle authentication
-    let auth = if let Some(token) = cli.auth_token {
-        Some(gcp::github::Authentication {
-            token,
-            scopes: vec![],
-            expires_at: None,
-            source: gcp::github::AuthSource::CommandLine,
-        })
-    } else {
-        // Try environment variable
-        match gcp::github::Authentication::from_env() {
-            Ok(Some(auth)) => Some(auth),
-            Ok(None) => None,
-            Err(e) => {
-                error!("Authentication error: {}", e);
-                std::process::exit(1);
+    let output = cli.output.unwrap_or_default();
+    if let Some(list) = cli.from_file.clone() {
+        let exit_code = tokio::select! {
+            exit_code = run_batch(&cli, &config_file, &list) => exit_code,
+            _ = tokio::signal::ctrl_c() => {
+                error!("{}", gcp::GcpError::Cancelled);
+                gcp::error::exit_code::CANCELLED
             }
-        }
-    };
+        };
+        std::process::exit(exit_code);
+    }
 
-    // Create configuration
-    let config = gcp::Config {
-        github: gcp::GitHubConfig {
-            api_url: "https://api.github.com".to_string(),
-            max_concurrent_requests: cli.max_concurrent,
-            retry_attempts: cli.retry,
-            rate_limit_buffer: 5,
-        },
-        download: gcp::DownloadConfig {
-            chunk_size: 1024 * 1024, // 1MB
-            max_file_size: 100 * 1024 * 1024, // 100MB
-            timeout_seconds: cli.timeout,
-        },
-        filesystem: gcp::FilesystemConfig {
-            default_permissions: Some(0o644),
-            preserve_timestamps: cli.preserve_timestamps,
-            create_intermediate_dirs: true,
-        },
+    let mut source = gcp::output::CopySource {
+        source: cli.source.clone().expect("source is required without a subcommand"),
+        ..Default::default()
     };
 
-    // Create GitHub client
-    let github_client = match gcp::github::GitHubClient::new(config.clone(), auth).await {
-        Ok(client) => std::sync::Arc::new(client),
-        Err(e) => {
-            error!("Failed to create GitHub client: {}", e);
-            std::process::exit(1);
-        }
+    use gcp::output::CopyOutcome;
+
+    let clients = Clients::default();
+    let destination = cli.destination.clone().unwrap_or_else(|| PathBuf::from("."));
+    let result = tokio::select! {
+        result = run_copy(&cli, &config_file, &clients, &mut source, destination, output) => result,
+        _ = tokio::signal::ctrl_c() => Err(gcp::GcpError::Cancelled),
     };
 
+    let exit_code = gcp::output::copy_exit_code(&result);
+    match output {
+        gcp::output::OutputFormat::Human => match &result {
+            Ok(CopyOutcome::Copied(report)) if report.is_partial() => {
+                for failed in &report.errors {
+                    error!("Failed to download {}: {}", failed.repo_path, failed.message);
+                }
+                error!("{} of {} files failed", report.errors.len(), report.errors.len() + report.files.len());
+            }
+            Ok(CopyOutcome::Copied(_)) => info!("GitHub Copy Tool finished successfully"),
+            Ok(CopyOutcome::Planned(plan)) => print_plan(plan),
+            Err(e) => error!("{}", e),
+        },
+        gcp::output::OutputFormat::Json => print_or_exit(&gcp::output::CopyDocument::new(&source, &result)),
+        gcp::output::OutputFormat::Ndjson => print_or_exit(&gcp::output::CopyEvent::finish(&result)),
+    }
+
+    std::process::exit(exit_code);
+}
+
+/// Copy the source URL to its destination
+///
+/// `source` is filled in as the copy is resolved, so machine-readable output can
+/// report how far it got even when the copy fails. With `OutputFormat::Json`
+/// nothing is printed; the caller reports the outcome.
+async fn run_copy(
+    cli: &Cli,
+    config_file: &gcp::settings::ConfigFile,
+    clients: &Clients,
+    source: &mut gcp::output::CopySource,
+    destination: PathBuf,
+    output: gcp::output::OutputFormat,
+) -> gcp::Result<gcp::output::CopyOutcome> {
+    use gcp::output::{CopyEvent, CopyOutcome, OutputFormat};
+
+    info!("Starting GitHub Copy Tool");
+    info!("Source: {}", source.source);
+    info!("Destination: {}", destination.display());
+
+    let host = cli.host.as_deref().unwrap_or(gcp::github::DEFAULT_HOST);
+
+    // Parse and validate the GitHub URL
+    let github_url = gcp::github::GitHubUrl::parse_with_host(&source.source, host)?;
+    debug!("Parsed GitHub URL: {:?}", github_url);
+    source.host = Some(github_url.host.clone());
+    source.owner = Some(github_url.owner.clone());
+    source.repo = Some(github_url.repo.clone());
+    source.ref_ = Some(github_url.ref_.clone().unwrap_or_else(|| "main".to_string()));
+    source.path = github_url.path.clone();
+
+    let (github_client, auth) = clients.get(cli, config_file, &github_url).await?;
+
     // Determine final destination based on GitHub URL type
     let final_destination = match github_url.url_type {
+        // The archive itself is the destination
+        gcp::github::UrlType::File | gcp::github::UrlType::Folder if cli.archive.is_some() => {
+            cli.archive.clone().unwrap_or(destination)
+        }
         gcp::github::UrlType::File => {
             if destination.is_dir() {
                 // If destination is a directory, use the original filename
@@ -217,64 +543,828 @@ async fn main() {
             destination
         }
         gcp::github::UrlType::Repository => {
-            error!("Repository URLs are not supported. Use file or folder URLs only.");
-            std::process::exit(1);
+            return Err(gcp::GcpError::InvalidOperation {
+                operation: "copy".to_string(),
+                reason: "Repository URLs are not supported. Use file or folder URLs only.".to_string(),
+            });
         }
     };
 
+    if cli.delete && github_url.url_type == gcp::github::UrlType::File {
+        return Err(gcp::GcpError::InvalidOperation {
+            operation: "delete".to_string(),
+            reason: "--delete only applies to folder URLs".to_string(),
+        });
+    }
+    if cli.delete && cli.archive.is_some() {
+        return Err(gcp::GcpError::InvalidOperation {
+            operation: "delete".to_string(),
+            reason: "--delete does not apply to --archive, which always writes a new archive".to_string(),
+        });
+    }
+
+    // Reject an unknown archive extension before listing anything
+    if let Some(archive) = &cli.archive {
+        gcp::filesystem::ArchiveFormat::from_path(archive)?;
+    }
+
     info!("Final destination: {}", final_destination.display());
+    source.destination = Some(final_destination.clone());
 
+    // Scripts get the exact commit that was copied; this costs one request
+    if output.is_machine_readable() {
+        match github_client.resolve_commit_sha(&github_url.owner, &github_url.repo, github_url.ref_.as_deref()).await {
+            Ok(sha) => source.commit_sha = Some(sha),
+            Err(e) => debug!("Could not resolve commit SHA: {}", e),
+        }
+    }
+    if output == OutputFormat::Ndjson {
+        print_or_exit(&CopyEvent::Start(source));
+    }
+
+    // A dry run lists the source and resolves every file against the destination,
+    // but writes nothing
     if cli.dry_run {
-        info!("DRY RUN: Would download from {}", cli.source);
-        info!("DRY RUN: Would save to {}", final_destination.display());
-        return;
+        let plan = match github_url.url_type {
+            _ if cli.archive.is_some() => {
+                gcp::downloader::FolderDownloader::new(github_client.clone())
+                    .plan_archive(&github_url)
+                    .await?
+            }
+            gcp::github::UrlType::File => {
+                gcp::downloader::FileDownloader::new(github_client.clone())
+                    .plan_file(&github_url, &final_destination, cli.force)
+                    .await?
+            }
+            _ => {
+                gcp::downloader::FolderDownloader::new(github_client.clone())
+                    .plan_folder(&github_url, &final_destination, cli.force)
+                    .await?
+            }
+        };
+        if output == OutputFormat::Ndjson {
+            for file in &plan.files {
+                print_or_exit(&CopyEvent::Planned(file));
+            }
+        }
+        return Ok(CopyOutcome::Planned(plan));
     }
 
-    // Create progress reporter if needed
-    let progress = if cli.progress || github_url.url_type == gcp::github::UrlType::Folder {
+    // Create progress reporter if needed; machine-readable output and concurrent
+    // batch copies stay free of it
+    let progress = if output.is_machine_readable() || cli.from_file.is_some() {
+        None
+    } else if cli.progress || github_url.url_type == gcp::github::UrlType::Folder {
         Some(std::sync::Arc::new(gcp::downloader::ProgressReporter::new_spinner("Downloading...")))
     } else {
         None
     };
 
     // Execute download based on URL type
-    let result = match github_url.url_type {
+    let report = match github_url.url_type {
+        _ if cli.archive.is_some() => {
+            info!("Archiving to {}", final_destination.display());
+            let archiver = folder_downloader(github_client.clone(), progress, output);
+            let plan = archiver.plan_archive(&github_url).await?;
+            let mut archive = gcp::filesystem::ArchiveWriter::create(&final_destination)?;
+            let report = archiver.archive_plan(&github_url, &plan, &mut archive).await?;
+            if report.is_partial() {
+                warn!("{} was not written because some files failed", final_destination.display());
+            } else if output == OutputFormat::Human && !cli.quiet {
+                println!("✓ Archived {} file(s) to {}", report.files.len(), final_destination.display());
+            }
+            report
+        }
         gcp::github::UrlType::File => {
             info!("Downloading single file");
-            let file_downloader = gcp::downloader::FileDownloader::new(github_client.clone())
-                .with_progress(progress.unwrap_or_else(|| std::sync::Arc::new(gcp::downloader::ProgressReporter::new(1))));
+            let mut file_downloader = gcp::downloader::FileDownloader::new(github_client.clone());
+            if let Some(progress) = progress {
+                file_downloader = file_downloader.with_progress(progress);
+            }
 
-            file_downloader.download_file(&github_url, &final_destination, cli.force).await
+            let file = file_downloader.download_file_with_sha(&github_url, None, &final_destination, cli.force).await?;
+            if output == OutputFormat::Ndjson {
+                print_or_exit(&CopyEvent::File(&file));
+            }
+            if output == OutputFormat::Human && !cli.quiet {
+                println!("✓ Successfully copied to {}", file.local_path.display());
+            }
+            gcp::downloader::DownloadReport { files: vec![file], ..Default::default() }
         }
         gcp::github::UrlType::Folder => {
             info!("Downloading folder");
-            let folder_downloader = gcp::downloader::FolderDownloader::new(github_client.clone())
-                .with_progress(progress.unwrap_or_else(|| std::sync::Arc::new(gcp::downloader::ProgressReporter::new_spinner("Downloading folder..."))));
-
-            match folder_downloader.download_folder(&github_url, &final_destination, cli.force).await {
-                Ok(count) => {
-                    info!("Downloaded {} files", count);
-                    Ok(final_destination)
-                }
-                Err(e) => Err(e)
+            let report = folder_downloader(github_client.clone(), progress, output).download_folder_report(&github_url, &final_destination, cli.force).await?;
+            info!("Downloaded {} files", report.files.len());
+            if output == OutputFormat::Human && !cli.quiet && !report.is_partial() {
+                println!("✓ Successfully copied to {}", final_destination.display());
             }
+            report
         }
         gcp::github::UrlType::Repository => {
             unreachable!() // Handled above
         }
     };
 
-    match result {
-        Ok(path) => {
-            if !cli.quiet {
-                println!("✓ Successfully copied to {}", path.display());
+    // Let git's credential helper know the credential worked
+    if let Some(Err(e)) = auth.as_ref().map(|auth| auth.approve()) {
+        debug!("{}", e);
+    }
+
+    Ok(CopyOutcome::Copied(report))
+}
+
+/// A folder downloader reporting to `progress`, and streaming NDJSON events when asked
+fn folder_downloader(
+    github_client: std::sync::Arc<gcp::github::GitHubClient>,
+    progress: Option<std::sync::Arc<gcp::downloader::ProgressReporter>>,
+    output: gcp::output::OutputFormat,
+) -> gcp::downloader::FolderDownloader {
+    use gcp::output::CopyEvent;
+
+    let mut folder_downloader = gcp::downloader::FolderDownloader::new(github_client);
+    if let Some(progress) = progress {
+        folder_downloader = folder_downloader.with_progress(progress);
+    }
+    if output == gcp::output::OutputFormat::Ndjson {
+        folder_downloader = folder_downloader.with_events(std::sync::Arc::new(|event| match event {
+            gcp::downloader::DownloadEvent::Downloaded(file) => print_or_exit(&CopyEvent::File(file)),
+            gcp::downloader::DownloadEvent::Failed(failed) => print_or_exit(&CopyEvent::FileError(failed)),
+            gcp::downloader::DownloadEvent::Deleted(path) => print_or_exit(&CopyEvent::Deleted { path }),
+        }));
+    }
+    folder_downloader
+}
+
+/// List a remote path (`gcp ls`)
+async fn run_ls(
+    cli: &mut Cli,
+    config_file: &gcp::settings::ConfigFile,
+    url: &str,
+    long: bool,
+    recursive: bool,
+    tree: bool,
+) -> gcp::Result<()> {
+    use gcp::output::{ListDocument, ListEntry, OutputFormat};
+
+    let host = cli.host.clone().unwrap_or_else(|| gcp::github::DEFAULT_HOST.to_string());
+    let mut github_url = gcp::github::GitHubUrl::parse_with_host(url, &host)?;
+    let (github_client, _) = connect(cli, config_file, &github_url).await?;
+
+    // A repository URL names no ref; list its default branch
+    if github_url.ref_.is_none() {
+        let info = github_client.get_repository_info(&github_url.owner, &github_url.repo).await?;
+        github_url.ref_ = Some(info.default_branch);
+    }
+
+    let lister = gcp::downloader::FolderDownloader::new(github_client);
+    let entries = if recursive {
+        lister.list_recursive(&github_url).await?
+    } else {
+        lister.get_folder_contents(&github_url).await?
+    };
+
+    match cli.output.unwrap_or_default() {
+        OutputFormat::Json => print_or_exit(&ListDocument {
+            source: url,
+            ref_: github_url.ref_.as_deref(),
+            entries: entries.iter().map(ListEntry::from).collect(),
+        }),
+        OutputFormat::Ndjson => {
+            for entry in &entries {
+                print_or_exit(&ListEntry::from(entry));
+            }
+        }
+        OutputFormat::Human => {
+            let root = github_url.path.as_deref().unwrap_or("").trim_matches('/');
+            let prefixes = if tree {
+                let root_label = if root.is_empty() { format!("{}/{}", github_url.owner, github_url.repo) } else { root.to_string() };
+                println!("{}", root_label);
+                let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+                gcp::output::tree_prefixes(root, &paths)
+            } else {
+                vec![String::new(); entries.len()]
+            };
+
+            for (entry, prefix) in entries.iter().zip(prefixes) {
+                // Recursive listings show paths relative to the listed folder, like `find`
+                let mut name = if recursive && !tree {
+                    entry.path.strip_prefix(root).map(|rest| rest.trim_start_matches('/')).unwrap_or(&entry.path).to_string()
+                } else {
+                    entry.name.clone()
+                };
+                if entry.is_directory() {
+                    name.push('/');
+                }
+
+                if long {
+                    let size = if entry.is_directory() { "-".to_string() } else { entry.size.to_string() };
+                    let sha = entry.sha.get(..7).unwrap_or(&entry.sha);
+                    println!("{:<9} {:>10}  {}  {}{}", entry.file_type, size, sha, prefix, name);
+                } else {
+                    println!("{}{}", prefix, name);
+                }
             }
         }
+    }
+
+    Ok(())
+}
+
+/// Describe a repository, ref and path (`gcp info`)
+async fn run_info(cli: &mut Cli, config_file: &gcp::settings::ConfigFile, url: &str) -> gcp::Result<()> {
+    use gcp::output::InfoDocument;
+
+    let host = cli.host.clone().unwrap_or_else(|| gcp::github::DEFAULT_HOST.to_string());
+    let mut github_url = gcp::github::GitHubUrl::parse_with_host(url, &host)?;
+    let (github_client, _) = connect(cli, config_file, &github_url).await?;
+
+    let repository = github_client.get_repository_info(&github_url.owner, &github_url.repo).await?;
+    let ref_ = github_url.ref_.get_or_insert_with(|| repository.default_branch.clone()).clone();
+    let path = github_url.path.clone().unwrap_or_default();
+
+    // History isn't cached, so offline runs report what the cache does know
+    fn optional<T>(result: gcp::Result<Option<T>>) -> gcp::Result<Option<T>> {
+        match result {
+            Err(gcp::GcpError::NotCached { item }) => {
+                debug!("Not available offline: {}", item);
+                Ok(None)
+            }
+            result => result,
+        }
+    }
+    let commit_sha = optional(github_client.resolve_commit_sha(&github_url.owner, &github_url.repo, Some(&ref_)).await.map(Some))?;
+    let last_commit = optional(github_client.last_commit(&github_url.owner, &github_url.repo, &path, Some(&ref_)).await)?;
+
+    let entries = gcp::downloader::FolderDownloader::new(github_client).list_recursive(&github_url).await?;
+    let mut document = InfoDocument::new(url, &repository, &ref_, &path, &entries);
+    document.commit_sha = commit_sha.as_deref();
+    document.last_commit = last_commit.as_ref();
+
+    if cli.output.unwrap_or_default().is_machine_readable() {
+        print_or_exit(&document);
+        return Ok(());
+    }
+
+    println!("Repository:  {}{}", repository.full_name, if repository.private { " (private)" } else { "" });
+    if let Some(description) = &repository.description {
+        println!("Description: {}", description);
+    }
+    println!("URL:         {}", repository.html_url);
+    println!("Default:     {}", repository.default_branch);
+    if let Some(language) = &repository.language {
+        println!("Language:    {}", language);
+    }
+    println!("License:     {}", repository.license.as_deref().unwrap_or("none"));
+    println!("Stars:       {}", repository.stargazers_count);
+    println!("Ref:         {}{}", ref_, document.commit_sha.map(|sha| format!(" ({})", sha)).unwrap_or_default());
+    println!("Path:        {}", if path.is_empty() { "/" } else { &path });
+    println!("Size:        {} in {} file(s)", indicatif::HumanBytes(document.size), document.files);
+    if let Some(commit) = &last_commit {
+        let summary = commit.message.lines().next().unwrap_or("");
+        println!("Last commit: {} {}", commit.sha.get(..7).unwrap_or(&commit.sha), summary);
+        println!("             by {}{}",
+            commit.author.as_deref().unwrap_or("unknown"),
+            commit.date.map(|date| format!(" on {}", date.format("%Y-%m-%d %H:%M UTC"))).unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// Compare a remote folder with a local directory (`gcp diff`)
+async fn run_diff(
+    cli: &mut Cli,
+    config_file: &gcp::settings::ConfigFile,
+    url: &str,
+    local_dir: &std::path::Path,
+    patch: bool,
+) -> gcp::Result<()> {
+    use gcp::downloader::{DiffStatus, FileDownloader, FolderDownloader};
+    use gcp::output::{DiffDocument, DiffItem, OutputFormat};
+
+    let host = cli.host.clone().unwrap_or_else(|| gcp::github::DEFAULT_HOST.to_string());
+    let github_url = gcp::github::GitHubUrl::parse_with_host(url, &host)?;
+    let (github_client, _) = connect(cli, config_file, &github_url).await?;
+
+    let entries = FolderDownloader::new(github_client.clone()).diff(&github_url, local_dir).await?;
+
+    // Only modified files are fetched, and only to render their patch
+    let downloader = FileDownloader::new(github_client);
+    let mut patches = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let patch = match (&entry.repo_path, &entry.remote_sha) {
+            (Some(repo_path), Some(sha)) if patch && entry.status == DiffStatus::Modified => {
+                let file_url = gcp::github::GitHubUrl {
+                    path: Some(repo_path.clone()),
+                    url_type: gcp::github::UrlType::File,
+                    ..github_url.clone()
+                };
+                let remote = downloader.fetch_content(&file_url, Some(sha)).await?;
+                let local_path = local_dir.join(&entry.path);
+                let local = std::fs::read(&local_path).map_err(|e| gcp::GcpError::FileIo { path: local_path, source: e })?;
+                Some(gcp::downloader::diff::unified_diff(&entry.path, &local, &remote))
+            }
+            _ => None,
+        };
+        patches.push(patch);
+    }
+
+    match cli.output.unwrap_or_default() {
+        OutputFormat::Json => print_or_exit(&DiffDocument {
+            source: url,
+            destination: local_dir,
+            ref_: github_url.ref_.as_deref(),
+            entries: entries.iter().zip(patches).map(|(entry, patch)| DiffItem { entry, patch }).collect(),
+        }),
+        OutputFormat::Ndjson => {
+            for (entry, patch) in entries.iter().zip(patches) {
+                print_or_exit(&DiffItem { entry, patch });
+            }
+        }
+        OutputFormat::Human => {
+            for entry in &entries {
+                println!("{}  {}", entry.status.code(), entry.path);
+            }
+            for patch in patches.iter().flatten() {
+                print!("{}", patch);
+            }
+
+            let count = |status| entries.iter().filter(|entry| entry.status == status).count();
+            if entries.is_empty() {
+                info!("No differences");
+            } else {
+                info!("{} added, {} removed, {} modified",
+                      count(DiffStatus::Added), count(DiffStatus::Removed), count(DiffStatus::Modified));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Vendor the manifest's sources (`gcp vendor`), or re-resolve them all (`gcp update`)
+///
+/// `gcp vendor` only resolves sources the lock doesn't have yet; `gcp update`
+/// resolves every source and only rewrites the lock.
+async fn run_vendor(
+    cli: &mut Cli,
+    config_file: &gcp::settings::ConfigFile,
+    manifest_path: &std::path::Path,
+    update: bool,
+) -> gcp::Result<()> {
+    use gcp::vendor::{Lockfile, Manifest, VendorStats};
+
+    let manifest = Manifest::load(manifest_path)?;
+    let base = manifest_path.parent().unwrap_or_else(|| std::path::Path::new(""));
+    let lock_path = base.join(gcp::vendor::LOCK_FILE);
+    let previous = Lockfile::load(&lock_path)?.unwrap_or_default();
+
+    let mut lock = Lockfile::default();
+    let mut lock_changed = previous.sources.len() != manifest.sources.len();
+    let mut totals = VendorStats::default();
+    let clients = Clients::default();
+
+    for source in &manifest.sources {
+        let host = cli.host.clone().unwrap_or_else(|| gcp::github::DEFAULT_HOST.to_string());
+        let github_url = gcp::github::GitHubUrl::parse_with_host(&source.url, &host)?;
+        let (client, _) = clients.get(cli, config_file, &github_url).await?;
+
+        let locked = match previous.find(source) {
+            Some(locked) if !update => locked.clone(),
+            existing => {
+                let locked = gcp::vendor::resolve(&client, source).await?;
+                let short = |sha: &str| sha.get(..7).unwrap_or(sha).to_string();
+                if !cli.quiet {
+                    match existing {
+                        Some(old) if old.commit == locked.commit => println!("  {}: {} (unchanged)", source.url, short(&locked.commit)),
+                        Some(old) => println!("  {}: {} -> {}", source.url, short(&old.commit), short(&locked.commit)),
+                        None => println!("  {}: locked at {}", source.url, short(&locked.commit)),
+                    }
+                }
+                lock_changed |= existing.map_or(true, |old| old.commit != locked.commit || old.files != locked.files);
+                locked
+            }
+        };
+
+        if !update {
+            let stats = gcp::vendor::vendor_source(&client, &locked, base).await?;
+            totals.written += stats.written;
+            totals.unchanged += stats.unchanged;
+        }
+        lock.sources.push(locked);
+    }
+
+    if lock_changed {
+        lock.save(&lock_path)?;
+        info!("Wrote {}", lock_path.display());
+    }
+    if !update && !cli.quiet {
+        println!("✓ Vendored {} source(s): {} file(s) written, {} unchanged",
+                 manifest.sources.len(), totals.written, totals.unchanged);
+    }
+    Ok(())
+}
+
+/// Verify the vendored files against gcp.lock (`gcp check`)
+fn run_check(cli: &Cli, manifest_path: &std::path::Path) -> gcp::Result<()> {
+    use gcp::output::{CheckDocument, OutputFormat};
+    use gcp::vendor::{CheckStatus, Lockfile, Manifest};
+
+    let manifest = Manifest::load(manifest_path)?;
+    let base = manifest_path.parent().unwrap_or_else(|| std::path::Path::new(""));
+    let lock_path = base.join(gcp::vendor::LOCK_FILE);
+    let lock = Lockfile::load(&lock_path)?.unwrap_or_default();
+
+    let issues = gcp::vendor::check(&manifest, &lock, base);
+    let files: usize = lock.sources.iter().map(|source| source.files.len()).sum();
+    let failure = (!issues.is_empty()).then(|| gcp::GcpError::InvalidOperation {
+        operation: "check".to_string(),
+        reason: format!("{} path(s) differ from {}", issues.len(), gcp::vendor::LOCK_FILE),
+    });
+
+    match cli.output.unwrap_or_default() {
+        OutputFormat::Json => {
+            print_or_exit(&CheckDocument { lock: &lock_path, files, issues: &issues });
+            // The document already lists what differs; only the exit code is left to report
+            if let Some(e) = failure {
+                std::process::exit(e.exit_code());
+            }
+            return Ok(());
+        }
+        OutputFormat::Ndjson => {
+            for issue in &issues {
+                print_or_exit(issue);
+            }
+        }
+        OutputFormat::Human if !cli.quiet => {
+            for issue in &issues {
+                let status = match issue.status {
+                    CheckStatus::Missing => "missing ",
+                    CheckStatus::Modified => "modified",
+                    CheckStatus::Unlocked => "unlocked",
+                };
+                println!("{}  {}", status, issue.path);
+            }
+            if failure.is_none() {
+                println!("✓ {} file(s) match {}", files, gcp::vendor::LOCK_FILE);
+            }
+        }
+        OutputFormat::Human => {}
+    }
+
+    failure.map_or(Ok(()), Err)
+}
+
+/// Stream a remote file to stdout (`gcp cat`)
+async fn run_cat(cli: &mut Cli, config_file: &gcp::settings::ConfigFile, url: &str) -> gcp::Result<()> {
+    use std::io::Write;
+
+    let host = cli.host.clone().unwrap_or_else(|| gcp::github::DEFAULT_HOST.to_string());
+    let github_url = gcp::github::GitHubUrl::parse_with_host(url, &host)?;
+    if github_url.url_type != gcp::github::UrlType::File {
+        return Err(gcp::GcpError::InvalidOperation {
+            operation: "cat".to_string(),
+            reason: "Only file URLs can be written to stdout".to_string(),
+        });
+    }
+
+    let (github_client, auth) = connect(cli, config_file, &github_url).await?;
+    let content = gcp::downloader::FileDownloader::new(github_client)
+        .fetch_content(&github_url, None)
+        .await?;
+
+    let mut stdout = std::io::stdout().lock();
+    match stdout.write_all(&content).and_then(|_| stdout.flush()) {
+        // The reader went away (`gcp cat ... | head`); that is not an error
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+        _ => {}
+    }
+
+    if let Some(Err(e)) = auth.as_ref().map(|auth| auth.approve()) {
+        debug!("{}", e);
+    }
+    Ok(())
+}
+
+/// Discover credentials for the URL's host and create a client for its API
+///
+/// A GitHub App installation replaces token discovery entirely. The token-based
+/// authentication is returned so a successful run can approve it with git's
+/// credential helper.
+async fn connect(
+    cli: &Cli,
+    config_file: &gcp::settings::ConfigFile,
+    github_url: &gcp::github::GitHubUrl,
+) -> gcp::Result<Connection> {
+    let app = match (cli.app_id, cli.app_installation_id, &cli.app_private_key) {
+        (Some(app_id), Some(installation_id), Some(key_path)) => {
+            Some(gcp::github::GitHubApp::from_key_file(app_id, installation_id, key_path)?)
+        }
+        _ => None,
+    };
+
+    // Handle authentication: CLI > environment > config file > gh CLI
+    let auth = match app {
+        Some(_) => None,
+        None => gcp::github::Authentication::discover(cli.auth_token.clone(), &github_url.host, config_file)?,
+    };
+
+    // Target the API of whichever host the URL belongs to
+    let config = cli.build_config(github_config_for(&github_url.host, config_file));
+
+    let client = match app {
+        Some(app) => gcp::github::GitHubClient::with_app(config, app).await?,
+        None => gcp::github::GitHubClient::new(config, auth.clone()).await?,
+    };
+    Ok((std::sync::Arc::new(client), auth))
+}
+
+/// A host's API client and the token-based authentication it uses, if any
+type Connection = (std::sync::Arc<gcp::github::GitHubClient>, Option<gcp::github::Authentication>);
+
+/// API clients shared by every copy of a run, one per host
+///
+/// Sharing a client shares its cache and token validation. Each host connects
+/// once, without holding up copies from other hosts; a failed connection is
+/// retried by the next copy that needs it.
+#[derive(Default)]
+struct Clients(std::sync::Mutex<std::collections::HashMap<String, std::sync::Arc<tokio::sync::OnceCell<Connection>>>>);
+
+impl Clients {
+    async fn get(
+        &self,
+        cli: &Cli,
+        config_file: &gcp::settings::ConfigFile,
+        github_url: &gcp::github::GitHubUrl,
+    ) -> gcp::Result<Connection> {
+        let cell = self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(github_url.host.clone())
+            .or_default()
+            .clone();
+        cell.get_or_try_init(|| connect(cli, config_file, github_url)).await.cloned()
+    }
+}
+
+/// Copy every source listed in `list` (`--from-file`), returning the exit code
+///
+/// Up to `--max-concurrent` sources are copied at once through shared clients.
+/// Each source is reported as it completes, followed by one aggregated summary.
+async fn run_batch(cli: &Cli, config_file: &gcp::settings::ConfigFile, list: &std::path::Path) -> i32 {
+    use futures::StreamExt;
+    use gcp::output::{BatchSummary, CopyDocument, CopyEvent, CopyOutcome, CopySource, OutputFormat};
+
+    let output = cli.output.unwrap_or_default();
+    let entries = match read_batch(list) {
+        Ok(entries) => entries,
         Err(e) => {
-            error!("Download failed: {}", e);
-            std::process::exit(1);
+            if output.is_machine_readable() {
+                print_or_exit(&gcp::output::ErrorDocument { error: (&e).into() });
+            } else {
+                error!("{}", e);
+            }
+            return e.exit_code();
+        }
+    };
+
+    let clients = Clients::default();
+    let concurrency = cli.max_concurrent.unwrap_or(gcp::GitHubConfig::default().max_concurrent_requests).max(1);
+    // Events of concurrent copies would interleave, so each copy reports once it's done
+    let copy_output = if output == OutputFormat::Human { OutputFormat::Human } else { OutputFormat::Json };
+
+    let copies = futures::stream::iter(entries)
+        .map(|(url, destination)| {
+            let clients = &clients;
+            async move {
+                let mut source = CopySource { source: url, ..Default::default() };
+                let destination = destination.unwrap_or_else(|| PathBuf::from("."));
+                let result = run_copy(cli, config_file, clients, &mut source, destination, copy_output).await;
+                if output == OutputFormat::Ndjson {
+                    print_or_exit(&CopyEvent::Source(CopyDocument::new(&source, &result)));
+                }
+                (source, result)
+            }
+        })
+        .buffered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    let results: Vec<&gcp::Result<CopyOutcome>> = copies.iter().map(|(_, result)| result).collect();
+    let summary = BatchSummary::new(&results);
+    let exit_code = summary.exit_code;
+    match output {
+        OutputFormat::Human => {
+            for (source, result) in &copies {
+                match result {
+                    Ok(CopyOutcome::Planned(plan)) => {
+                        println!("{}:", source.source);
+                        print_plan(plan);
+                    }
+                    Ok(CopyOutcome::Copied(report)) => {
+                        for failed in &report.errors {
+                            error!("{}: failed to download {}: {}", source.source, failed.repo_path, failed.message);
+                        }
+                    }
+                    Err(e) => error!("{}: {}", source.source, e),
+                }
+            }
+            println!(
+                "Copied {} of {} source(s): {} file(s), {}; {} failed",
+                summary.succeeded,
+                summary.sources,
+                summary.files,
+                indicatif::HumanBytes(summary.total_bytes),
+                summary.failed,
+            );
+        }
+        OutputFormat::Json => print_or_exit(&gcp::output::BatchDocument {
+            sources: copies.iter().map(|(source, result)| CopyDocument::new(source, result)).collect(),
+            summary,
+        }),
+        OutputFormat::Ndjson => print_or_exit(&CopyEvent::Summary(summary)),
+    }
+
+    exit_code
+}
+
+/// Parse a `--from-file` list: one `<url> [destination]` per line
+///
+/// Blank lines and lines starting with `#` are ignored.
+fn read_batch(list: &std::path::Path) -> gcp::Result<Vec<(String, Option<PathBuf>)>> {
+    let content = if list.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(list).map_err(|e| gcp::GcpError::FileIo { path: list.to_path_buf(), source: e })?
+    };
+
+    let mut entries = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields[..] {
+            [url] => entries.push((url.to_string(), None)),
+            // Concurrent copies to stdout would interleave
+            [_, "-"] => {
+                return Err(gcp::GcpError::Config {
+                    message: format!("{}:{}: `-` (stdout) is not a valid destination in a batch; use `gcp cat` for each file", list.display(), number + 1),
+                })
+            }
+            [url, destination] => entries.push((url.to_string(), Some(PathBuf::from(destination)))),
+            _ => {
+                return Err(gcp::GcpError::Config {
+                    message: format!("{}:{}: expected `<url> [destination]`", list.display(), number + 1),
+                })
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Print a `--dry-run` plan: one line per file, then the totals
+fn print_plan(plan: &gcp::downloader::DownloadPlan) {
+    use gcp::downloader::{PlannedAction, SkipReason};
+    use indicatif::HumanBytes;
+
+    for file in &plan.files {
+        let detail = match (&file.symlink, &file.copied_from) {
+            (Some(target), _) => format!("symlink to {}", target),
+            (None, Some(source)) => format!("copy of {}, {}", source, HumanBytes(file.size)),
+            (None, None) => HumanBytes(file.size).to_string(),
+        };
+        match &file.action {
+            PlannedAction::Create => println!("create     {} -> {} ({})", file.repo_path, file.local_path.display(), detail),
+            PlannedAction::Overwrite => println!("overwrite  {} -> {} ({})", file.repo_path, file.local_path.display(), detail),
+            PlannedAction::Rename { to } => println!("rename     {} -> {} ({})", file.repo_path, to.display(), detail),
+            PlannedAction::Skip { reason } => {
+                let reason = match reason {
+                    SkipReason::Excluded => "matches --exclude",
+                    SkipReason::NotIncluded => "does not match --include",
+                    SkipReason::TooLarge => "exceeds the size limit",
+                    SkipReason::Submodule => "submodule",
+                    SkipReason::Symlink => "symlink",
+                    SkipReason::UnsafeSymlink => "symlink points outside the destination",
+                    SkipReason::Unchanged => "unchanged",
+                };
+                println!("skip       {} ({})", file.repo_path, reason);
+            }
+        }
+    }
+    for path in &plan.deletions {
+        println!("delete     {}", path.display());
+    }
+
+    let writes = plan.writes().count();
+    let deletions = if plan.deletions.is_empty() { String::new() } else { format!(", delete {}", plan.deletions.len()) };
+    println!(
+        "Dry run: would write {} file(s), {}, and skip {}{}; {} API call(s) ({} listing, {} symlink, {} metadata, {} download)",
+        writes,
+        HumanBytes(plan.total_bytes()),
+        plan.files.len() - writes,
+        deletions,
+        plan.api_calls(),
+        plan.listing_requests,
+        plan.symlink_requests,
+        plan.metadata_requests,
+        plan.download_requests(),
+    );
+}
+
+/// Write one line of JSON to stdout; a closed stdout ends the process
+fn print_or_exit<T: serde::Serialize + ?Sized>(value: &T) {
+    if let Err(e) = gcp::output::print_json_line(value) {
+        eprintln!("Error: failed to write output: {}", e);
+        std::process::exit(gcp::error::exit_code::FAILURE);
+    }
+}
+
+async fn run_auth(
+    command: AuthCommand,
+    host: &str,
+    cli_token: Option<String>,
+    config_file: &gcp::settings::ConfigFile,
+    config: gcp::Config,
+) -> gcp::Result<()> {
+    use gcp::github::{Authentication, AuthSource};
+    use gcp::settings::Credentials;
+
+    match command {
+        AuthCommand::Login { client_id, scopes } => {
+            let client_id = client_id
+                .or_else(|| config_file.host(host).and_then(|h| h.oauth_client_id.clone()))
+                .ok_or_else(|| gcp::GcpError::Config {
+                    message: format!(
+                        "No OAuth client ID for {}; pass --client-id or set hosts.\"{}\".oauth-client-id in the config file",
+                        host, host
+                    ),
+                })?;
+
+            let flow = gcp::github::device_flow::DeviceFlow::new(&format!("https://{}", host), &client_id);
+            let code = flow.request_code(&scopes).await?;
+            println!("First copy your one-time code: {}", code.user_code);
+            println!("Then open {} in your browser to authorize gcp", code.verification_uri);
+
+            let token = flow.poll(&code).await?;
+            let auth = Authentication {
+                token: token.access_token.clone(),
+                scopes: token.scopes.clone(),
+                expires_at: None,
+                source: AuthSource::ConfigFile,
+            };
+
+            let path = Credentials::default_path()?;
+            let mut credentials = Credentials::load(&path)?;
+            credentials.set(host, token.access_token, token.scopes);
+            credentials.save(&path)?;
+
+            println!("✓ Logged in to {} (token {} stored in {})", host, auth.mask_token(), path.display());
+        }
+        AuthCommand::Status => {
+            let auth = Authentication::discover(cli_token, host, config_file)?.ok_or_else(|| gcp::GcpError::Authentication {
+                reason: format!("Not logged in to {}; run `gcp auth login`", host),
+            })?;
+
+            println!("{}", host);
+            println!("  Source:  {:?}", auth.source);
+            println!("  Token:   {}", auth.mask_token());
+
+            // Validating against /user (or a cached validation) fills in scopes and expiry
+            let client = gcp::github::GitHubClient::new(config, Some(auth)).await?;
+            let auth = client.validate_authentication(true).await?.expect("client was created with a token");
+
+            if auth.scopes.is_empty() {
+                println!("  Scopes:  (not reported for this token type)");
+            } else {
+                println!("  Scopes:  {}", auth.scopes.join(", "));
+            }
+            match auth.expires_at {
+                Some(expires_at) => println!("  Expires: {}", expires_at.format("%Y-%m-%d %H:%M UTC")),
+                None => println!("  Expires: never"),
+            }
+        }
+        AuthCommand::Check { refresh } => {
+            let auth = Authentication::discover(cli_token, host, config_file)?.ok_or_else(|| gcp::GcpError::Authentication {
+                reason: format!("No credentials found for {}", host),
+            })?;
+            let source = auth.source.clone();
+
+            let client = gcp::github::GitHubClient::new(config, Some(auth)).await?;
+            let auth = client.validate_authentication(!refresh).await?.expect("client was created with a token");
+            println!("✓ Token for {} from {:?} is valid ({})", host, source, auth.mask_token());
+        }
+        AuthCommand::Logout => {
+            let path = Credentials::default_path()?;
+            let mut credentials = Credentials::load(&path)?;
+            if credentials.remove(host) {
+                credentials.save(&path)?;
+                println!("✓ Logged out of {}", host);
+            } else {
+                println!("No stored token for {}", host);
+            }
         }
     }
 
-    info!("GitHub Copy Tool finished successfully");
+    Ok(())
 }
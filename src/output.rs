@@ -0,0 +1,499 @@
+use std::io::Write;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+
+use crate::downloader::{DiffEntry, DownloadPlan, DownloadReport, DownloadedFile, FailedFile, PlannedFile};
+use crate::error::{exit_code, GcpError, Result};
+use crate::github::{CommitInfo, GitHubFile, RepositoryInfo};
+
+/// How results are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable messages
+    #[default]
+    Human,
+    /// A single JSON document once the command finishes
+    Json,
+    /// One JSON event per line as work progresses
+    Ndjson,
+}
+
+impl OutputFormat {
+    pub fn is_machine_readable(self) -> bool {
+        self != OutputFormat::Human
+    }
+}
+
+/// What a copy did, or with `--dry-run` what it would do
+#[derive(Debug, Clone)]
+pub enum CopyOutcome {
+    Copied(DownloadReport),
+    Planned(DownloadPlan),
+}
+
+/// A fatal error as reported in JSON output
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorInfo {
+    pub kind: &'static str,
+    pub message: String,
+    pub exit_code: i32,
+}
+
+impl From<&GcpError> for ErrorInfo {
+    fn from(error: &GcpError) -> Self {
+        Self {
+            kind: error.kind(),
+            message: error.to_string(),
+            exit_code: error.exit_code(),
+        }
+    }
+}
+
+/// What a copy was resolved to, filled in as far as it got
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CopySource {
+    pub source: String,
+    pub host: Option<String>,
+    pub owner: Option<String>,
+    pub repo: Option<String>,
+    #[serde(rename = "ref")]
+    pub ref_: Option<String>,
+    pub path: Option<String>,
+    /// Full SHA of the commit the ref resolved to
+    pub commit_sha: Option<String>,
+    pub destination: Option<PathBuf>,
+}
+
+/// The document written by `--output json`
+#[derive(Debug, Serialize)]
+pub struct CopyDocument<'a> {
+    #[serde(flatten)]
+    pub source: &'a CopySource,
+    pub dry_run: bool,
+    pub files: &'a [DownloadedFile],
+    pub errors: &'a [FailedFile],
+    /// Local files removed with `--delete`, or with `--dry-run` those that would be
+    #[serde(skip_serializing_if = "<[PathBuf]>::is_empty")]
+    pub deleted: &'a [PathBuf],
+    /// With `--dry-run`, every file and what would happen to it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<&'a [PlannedFile]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_calls: Option<usize>,
+    pub total_bytes: u64,
+    pub exit_code: i32,
+    pub error: Option<ErrorInfo>,
+}
+
+/// An event written by `--output ndjson`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CopyEvent<'a> {
+    Start(&'a CopySource),
+    File(&'a DownloadedFile),
+    FileError(&'a FailedFile),
+    /// A file in a `--dry-run` plan
+    Planned(&'a PlannedFile),
+    /// A local file removed with `--delete`
+    Deleted { path: &'a std::path::Path },
+    /// One completed source of a `--from-file` batch
+    Source(CopyDocument<'a>),
+    /// The closing event of a `--from-file` batch
+    Summary(BatchSummary),
+    Finish {
+        files: usize,
+        errors: usize,
+        total_bytes: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        api_calls: Option<usize>,
+        exit_code: i32,
+        error: Option<ErrorInfo>,
+    },
+}
+
+/// A fatal error on its own, for commands other than copy
+#[derive(Debug, Serialize)]
+pub struct ErrorDocument {
+    pub error: ErrorInfo,
+}
+
+/// One entry of `gcp ls`
+#[derive(Debug, Serialize)]
+pub struct ListEntry<'a> {
+    pub path: &'a str,
+    pub name: &'a str,
+    #[serde(rename = "type")]
+    pub file_type: &'a str,
+    pub size: i64,
+    pub sha: &'a str,
+}
+
+impl<'a> From<&'a GitHubFile> for ListEntry<'a> {
+    fn from(file: &'a GitHubFile) -> Self {
+        Self {
+            path: &file.path,
+            name: &file.name,
+            file_type: &file.file_type,
+            size: file.size,
+            sha: &file.sha,
+        }
+    }
+}
+
+/// The document written by `gcp ls --output json`
+#[derive(Debug, Serialize)]
+pub struct ListDocument<'a> {
+    pub source: &'a str,
+    #[serde(rename = "ref")]
+    pub ref_: Option<&'a str>,
+    pub entries: Vec<ListEntry<'a>>,
+}
+
+/// The document written by `gcp info --output json`
+#[derive(Debug, Serialize)]
+pub struct InfoDocument<'a> {
+    pub source: &'a str,
+    pub repository: &'a RepositoryInfo,
+    #[serde(rename = "ref")]
+    pub ref_: &'a str,
+    pub commit_sha: Option<&'a str>,
+    pub path: &'a str,
+    pub last_commit: Option<&'a CommitInfo>,
+    /// Total bytes of all files below the path
+    pub size: u64,
+    pub files: usize,
+}
+
+impl<'a> InfoDocument<'a> {
+    /// Summarise the recursive listing of `path`
+    pub fn new(source: &'a str, repository: &'a RepositoryInfo, ref_: &'a str, path: &'a str, entries: &[GitHubFile]) -> Self {
+        let files: Vec<&GitHubFile> = entries.iter().filter(|entry| !entry.is_directory()).collect();
+        Self {
+            source,
+            repository,
+            ref_,
+            commit_sha: None,
+            path,
+            last_commit: None,
+            size: files.iter().map(|file| file.size.max(0) as u64).sum(),
+            files: files.len(),
+        }
+    }
+}
+
+/// One changed path in `gcp diff` JSON output, with its patch when requested
+#[derive(Debug, Serialize)]
+pub struct DiffItem<'a> {
+    #[serde(flatten)]
+    pub entry: &'a DiffEntry,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patch: Option<String>,
+}
+
+/// The document written by `gcp diff --output json`
+#[derive(Debug, Serialize)]
+pub struct DiffDocument<'a> {
+    pub source: &'a str,
+    pub destination: &'a std::path::Path,
+    #[serde(rename = "ref")]
+    pub ref_: Option<&'a str>,
+    pub entries: Vec<DiffItem<'a>>,
+}
+
+/// The document written by `gcp check --output json`
+#[derive(Debug, Serialize)]
+pub struct CheckDocument<'a> {
+    pub lock: &'a std::path::Path,
+    /// Files the lock records
+    pub files: usize,
+    pub issues: &'a [crate::vendor::CheckIssue],
+}
+
+/// Box-drawing prefixes for a tree view of depth-first ordered `paths` below `root`
+///
+/// Returns one prefix per path, e.g. `├── ` or `│   └── `.
+pub fn tree_prefixes(root: &str, paths: &[&str]) -> Vec<String> {
+    let root = root.trim_matches('/');
+    let depth_of = |path: &str| {
+        let relative = if root.is_empty() { path } else { path.strip_prefix(root).unwrap_or(path) };
+        relative.trim_matches('/').matches('/').count()
+    };
+    let depths: Vec<usize> = paths.iter().map(|path| depth_of(path)).collect();
+
+    // An entry is the last of its siblings when nothing at its depth follows
+    // before the tree climbs back above it
+    let is_last = |index: usize| {
+        let depth = depths[index];
+        match depths[index + 1..].iter().find(|&&other| other <= depth) {
+            Some(&other) => other < depth,
+            None => true,
+        }
+    };
+
+    let mut open = Vec::new();
+    let mut prefixes = Vec::with_capacity(paths.len());
+    for (index, &depth) in depths.iter().enumerate() {
+        open.truncate(depth);
+        let last = is_last(index);
+
+        let mut prefix: String = open.iter().map(|&more: &bool| if more { "│   " } else { "    " }).collect();
+        prefix.push_str(if last { "└── " } else { "├── " });
+        prefixes.push(prefix);
+
+        open.push(!last);
+    }
+    prefixes
+}
+
+/// Exit code for a finished copy: the error's category, or partial failure
+/// when some files could not be downloaded
+pub fn copy_exit_code(result: &Result<CopyOutcome>) -> i32 {
+    match result {
+        Ok(CopyOutcome::Copied(report)) if report.is_partial() => exit_code::PARTIAL_FAILURE,
+        Ok(_) => exit_code::SUCCESS,
+        Err(e) => e.exit_code(),
+    }
+}
+
+impl<'a> CopyDocument<'a> {
+    pub fn new(source: &'a CopySource, result: &'a Result<CopyOutcome>) -> Self {
+        let mut document = Self {
+            source,
+            dry_run: false,
+            files: &[],
+            errors: &[],
+            deleted: &[],
+            plan: None,
+            api_calls: None,
+            total_bytes: 0,
+            exit_code: copy_exit_code(result),
+            error: result.as_ref().err().map(ErrorInfo::from),
+        };
+
+        match result {
+            Ok(CopyOutcome::Copied(report)) => {
+                document.files = &report.files;
+                document.errors = &report.errors;
+                document.deleted = &report.deleted;
+                document.total_bytes = report.total_bytes();
+            }
+            Ok(CopyOutcome::Planned(plan)) => {
+                document.dry_run = true;
+                document.plan = Some(&plan.files);
+                document.deleted = &plan.deletions;
+                document.api_calls = Some(plan.api_calls());
+                document.total_bytes = plan.total_bytes();
+            }
+            Err(_) => {}
+        }
+        document
+    }
+}
+
+impl CopyEvent<'_> {
+    /// The closing event of a copy
+    pub fn finish(result: &Result<CopyOutcome>) -> CopyEvent<'static> {
+        let (files, errors, total_bytes, api_calls) = match result {
+            Ok(CopyOutcome::Copied(report)) => (report.files.len(), report.errors.len(), report.total_bytes(), None),
+            Ok(CopyOutcome::Planned(plan)) => (plan.writes().count(), 0, plan.total_bytes(), Some(plan.api_calls())),
+            Err(_) => (0, 0, 0, None),
+        };
+
+        CopyEvent::Finish {
+            files,
+            errors,
+            total_bytes,
+            api_calls,
+            exit_code: copy_exit_code(result),
+            error: result.as_ref().err().map(ErrorInfo::from),
+        }
+    }
+}
+
+/// Totals over every source of a `--from-file` batch
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BatchSummary {
+    pub sources: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub files: usize,
+    pub errors: usize,
+    pub total_bytes: u64,
+    pub exit_code: i32,
+}
+
+impl BatchSummary {
+    /// Aggregate the outcome of each source
+    ///
+    /// The exit code is that of the only failure category when every source
+    /// failed the same way, and `PARTIAL_FAILURE` whenever anything else failed.
+    pub fn new(results: &[&Result<CopyOutcome>]) -> Self {
+        let mut summary = BatchSummary { sources: results.len(), ..Default::default() };
+        let mut exit_codes = Vec::new();
+
+        for result in results {
+            let code = copy_exit_code(result);
+            if code == exit_code::SUCCESS {
+                summary.succeeded += 1;
+            } else {
+                summary.failed += 1;
+                exit_codes.push(code);
+            }
+            match result {
+                Ok(CopyOutcome::Copied(report)) => {
+                    summary.files += report.files.len();
+                    summary.errors += report.errors.len();
+                    summary.total_bytes += report.total_bytes();
+                }
+                Ok(CopyOutcome::Planned(plan)) => {
+                    summary.files += plan.writes().count();
+                    summary.total_bytes += plan.total_bytes();
+                }
+                Err(_) => {}
+            }
+        }
+
+        summary.exit_code = match exit_codes.first() {
+            None => exit_code::SUCCESS,
+            Some(&code) if summary.succeeded == 0 && exit_codes.iter().all(|&other| other == code) => code,
+            Some(_) => exit_code::PARTIAL_FAILURE,
+        };
+        summary
+    }
+}
+
+/// The document written by `--from-file --output json`
+#[derive(Debug, Serialize)]
+pub struct BatchDocument<'a> {
+    pub sources: Vec<CopyDocument<'a>>,
+    pub summary: BatchSummary,
+}
+
+/// Write `value` to stdout as a single line of JSON
+pub fn print_json_line<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::ConflictResolution;
+
+    #[test]
+    fn test_document_and_events_shape() {
+        let source = CopySource {
+            source: "https://github.com/o/r/tree/main/docs".to_string(),
+            ref_: Some("main".to_string()),
+            commit_sha: Some("abc123".to_string()),
+            ..Default::default()
+        };
+        let report = DownloadReport {
+            files: vec![DownloadedFile {
+                repo_path: "docs/a.md".to_string(),
+                local_path: PathBuf::from("out/a.md"),
+                size: 3,
+                sha: "deadbeef".to_string(),
+                conflict: ConflictResolution::Renamed("out/a_1.md".to_string()),
+            }],
+            errors: vec![FailedFile::new("docs/b.md", &GcpError::Cancelled)],
+            deleted: vec![],
+        };
+        let result = Ok(CopyOutcome::Copied(report));
+
+        let document = serde_json::to_value(CopyDocument::new(&source, &result)).unwrap();
+        assert_eq!(document["ref"], "main");
+        assert!(document.get("deleted").is_none());
+        assert_eq!(document["commit_sha"], "abc123");
+        assert_eq!(document["files"][0]["conflict"]["renamed"], "out/a_1.md");
+        assert_eq!(document["errors"][0]["kind"], "cancelled");
+        assert_eq!(document["exit_code"], exit_code::PARTIAL_FAILURE);
+        assert_eq!(document["dry_run"], false);
+        assert!(document.get("plan").is_none());
+
+        let start = serde_json::to_value(CopyEvent::Start(&source)).unwrap();
+        assert_eq!(start["event"], "start");
+        let finish = serde_json::to_value(CopyEvent::finish(&Err(GcpError::Cancelled))).unwrap();
+        assert_eq!(finish["event"], "finish");
+        assert_eq!(finish["error"]["kind"], "cancelled");
+        assert_eq!(finish["exit_code"], exit_code::CANCELLED);
+    }
+
+    #[test]
+    fn test_tree_prefixes() {
+        let paths = ["docs/a.md", "docs/guide", "docs/guide/intro.md", "docs/guide/setup.md", "docs/z.md"];
+        assert_eq!(
+            tree_prefixes("docs", &paths),
+            vec!["├── ", "├── ", "│   ├── ", "│   └── ", "└── "]
+        );
+
+        let nested_last = ["src", "src/lib.rs"];
+        assert_eq!(tree_prefixes("", &nested_last), vec!["└── ", "    └── "]);
+    }
+
+    #[test]
+    fn test_plan_document() {
+        use crate::downloader::{PlannedAction, SkipReason};
+
+        let planned = |path: &str, size, action| PlannedFile {
+            repo_path: path.to_string(),
+            local_path: PathBuf::from(path),
+            size,
+            sha: "deadbeef".to_string(),
+            action,
+            cached: false,
+            symlink: None,
+            copied_from: None,
+        };
+        let plan = DownloadPlan {
+            files: vec![
+                planned("a.md", 10, PlannedAction::Create),
+                planned("b.md", 5, PlannedAction::Rename { to: PathBuf::from("b_1.md") }),
+                planned("big.bin", 1 << 30, PlannedAction::Skip { reason: SkipReason::TooLarge }),
+            ],
+            listing_requests: 1,
+            symlink_requests: 0,
+            metadata_requests: 0,
+            deletions: vec![PathBuf::from("old.md")],
+        };
+        let result = Ok(CopyOutcome::Planned(plan));
+        let source = CopySource::default();
+
+        let document = serde_json::to_value(CopyDocument::new(&source, &result)).unwrap();
+        assert_eq!(document["dry_run"], true);
+        assert_eq!(document["total_bytes"], 15);
+        assert_eq!(document["api_calls"], 3);
+        assert_eq!(document["plan"][1]["action"], "rename");
+        assert_eq!(document["plan"][1]["to"], "b_1.md");
+        assert_eq!(document["plan"][2]["reason"], "too_large");
+        assert_eq!(document["deleted"][0], "old.md");
+        assert_eq!(document["exit_code"], exit_code::SUCCESS);
+    }
+
+    #[test]
+    fn test_batch_summary_exit_code() {
+        let copied = |files: usize| Ok(CopyOutcome::Copied(DownloadReport {
+            files: (0..files).map(|i| DownloadedFile {
+                repo_path: format!("f{}", i),
+                local_path: PathBuf::from(format!("f{}", i)),
+                size: 2,
+                sha: String::new(),
+                conflict: ConflictResolution::NoConflict,
+            }).collect(),
+            ..Default::default()
+        }));
+        let not_cached = || Err(GcpError::NotCached { item: "x".to_string() });
+
+        let (a, b, c) = (copied(2), copied(1), not_cached());
+        let summary = BatchSummary::new(&[&a, &b, &c]);
+        assert_eq!((summary.succeeded, summary.failed, summary.files, summary.total_bytes), (2, 1, 3, 6));
+        assert_eq!(summary.exit_code, exit_code::PARTIAL_FAILURE);
+
+        let (d, e) = (not_cached(), not_cached());
+        assert_eq!(BatchSummary::new(&[&d, &e]).exit_code, exit_code::NOT_FOUND);
+        assert_eq!(BatchSummary::new(&[&a]).exit_code, exit_code::SUCCESS);
+    }
+}
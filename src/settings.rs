@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error::{GcpError, Result};
+
+/// Persistent configuration read from `<config dir>/gcp/config.toml`
+///
+/// ```toml
+/// profile = "work"            # profile applied when --profile is not given
+///
+/// [defaults]
+/// max-concurrent = 20
+/// exclude = ["*.png"]
+///
+/// [hosts."ghe.example.com"]
+/// token = "ghp_..."
+/// api-url = "https://ghe.example.com/api/v3"
+///
+/// [profiles.work]
+/// host = "ghe.example.com"
+/// progress = true
+/// ```
+///
+/// Settings are layered with precedence CLI > environment > config file > built-in defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ConfigFile {
+    pub profile: Option<String>,
+    pub defaults: Defaults,
+    pub hosts: BTreeMap<String, HostConfig>,
+    pub profiles: BTreeMap<String, Defaults>,
+}
+
+/// Default values for CLI flags; unset fields fall through to the next layer
+///
+/// `--delete` has no default on purpose: it removes local files, and a
+/// configured default would turn every folder copy into an existing
+/// directory into a mirror that deletes whatever the source lacks.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Defaults {
+    pub host: Option<String>,
+    pub verbose: Option<bool>,
+    pub quiet: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub progress: Option<bool>,
+    pub force: Option<bool>,
+    pub preserve_timestamps: Option<bool>,
+    pub copy_symlinks: Option<bool>,
+    /// Octal mode for non-executable files, e.g. `"0644"`
+    #[serde(default, deserialize_with = "deserialize_mode")]
+    pub default_permissions: Option<u32>,
+    pub exclude: Option<Vec<String>>,
+    pub include: Option<Vec<String>>,
+    pub max_concurrent: Option<usize>,
+    pub timeout: Option<u64>,
+    pub retry: Option<u32>,
+    pub cache_dir: Option<PathBuf>,
+    pub no_cache: Option<bool>,
+    pub offline: Option<bool>,
+    pub auth_cache_ttl: Option<u64>,
+    pub output: Option<crate::output::OutputFormat>,
+}
+
+/// Per-host settings, keyed by web host (e.g. `github.com`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct HostConfig {
+    pub token: Option<String>,
+    pub api_url: Option<String>,
+    /// OAuth app client ID used by `gcp auth login`
+    pub oauth_client_id: Option<String>,
+}
+
+/// Tokens saved by `gcp auth login`, kept in `<config dir>/gcp/credentials.toml`
+///
+/// Stored separately from `config.toml` so the user's hand-written config is never
+/// rewritten, and written with owner-only permissions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Credentials {
+    pub hosts: BTreeMap<String, StoredToken>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct StoredToken {
+    pub token: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl ConfigFile {
+    /// Location of the config file: `<config dir>/gcp/config.toml`
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(crate::get_config_dir()?.join("config.toml"))
+    }
+
+    /// Load the config file at `path`; a missing file yields an empty configuration
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("No config file at {}", path.display());
+                return Ok(Self::default());
+            }
+            Err(e) => return Err(GcpError::FileIo { path: path.to_path_buf(), source: e }),
+        };
+
+        debug!("Loading config file {}", path.display());
+        Self::parse(&content).map_err(|e| GcpError::Config {
+            message: format!("{}: {}", path.display(), e),
+        })
+    }
+
+    /// Load the config file from its default location
+    pub fn load_default() -> Result<Self> {
+        Self::load(&Self::default_path()?)
+    }
+
+    pub fn parse(content: &str) -> std::result::Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Resolve the effective defaults: the selected profile layered over `[defaults]`
+    ///
+    /// `profile` overrides the file's own `profile` key. Naming an unknown profile is an error.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<Defaults> {
+        let name = match profile.or(self.profile.as_deref()) {
+            Some(name) => name,
+            None => return Ok(self.defaults.clone()),
+        };
+
+        match self.profiles.get(name) {
+            Some(overrides) => {
+                debug!("Using config profile '{}'", name);
+                Ok(self.defaults.clone().overlay(overrides.clone()))
+            }
+            None => Err(GcpError::Config {
+                message: format!("Unknown profile '{}' in config file", name),
+            }),
+        }
+    }
+
+    pub fn host(&self, host: &str) -> Option<&HostConfig> {
+        self.hosts.get(host)
+    }
+}
+
+impl Credentials {
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(crate::get_config_dir()?.join("credentials.toml"))
+    }
+
+    /// Load stored credentials; a missing file yields an empty store
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(GcpError::FileIo { path: path.to_path_buf(), source: e }),
+        };
+
+        toml::from_str(&content).map_err(|e| GcpError::Config {
+            message: format!("{}: {}", path.display(), e),
+        })
+    }
+
+    pub fn load_default() -> Result<Self> {
+        Self::load(&Self::default_path()?)
+    }
+
+    /// Write the store atomically, readable by the owner only
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self).map_err(|e| GcpError::Config {
+            message: format!("Failed to serialize credentials: {}", e),
+        })?;
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let io_err = |e: std::io::Error| GcpError::FileIo { path: path.to_path_buf(), source: e };
+
+        // NamedTempFile is created with mode 0600 on Unix
+        let mut temp = tempfile::NamedTempFile::new_in(dir).map_err(io_err)?;
+        temp.write_all(content.as_bytes()).map_err(io_err)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            temp.as_file()
+                .set_permissions(std::fs::Permissions::from_mode(0o600))
+                .map_err(io_err)?;
+        }
+        temp.persist(path).map_err(|e| io_err(e.error))?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, host: &str) -> Option<&StoredToken> {
+        self.hosts.get(host)
+    }
+
+    pub fn set(&mut self, host: &str, token: String, scopes: Vec<String>) {
+        self.hosts.insert(host.to_string(), StoredToken {
+            token,
+            scopes,
+            created_at: Some(Utc::now()),
+        });
+    }
+
+    /// Remove the token for `host`, returning whether one was stored
+    pub fn remove(&mut self, host: &str) -> bool {
+        self.hosts.remove(host).is_some()
+    }
+}
+
+impl Defaults {
+    /// Layer `other` on top of `self`; fields set in `other` win
+    pub fn overlay(self, other: Defaults) -> Defaults {
+        Defaults {
+            host: other.host.or(self.host),
+            verbose: other.verbose.or(self.verbose),
+            quiet: other.quiet.or(self.quiet),
+            dry_run: other.dry_run.or(self.dry_run),
+            progress: other.progress.or(self.progress),
+            force: other.force.or(self.force),
+            preserve_timestamps: other.preserve_timestamps.or(self.preserve_timestamps),
+            copy_symlinks: other.copy_symlinks.or(self.copy_symlinks),
+            default_permissions: other.default_permissions.or(self.default_permissions),
+            exclude: other.exclude.or(self.exclude),
+            include: other.include.or(self.include),
+            max_concurrent: other.max_concurrent.or(self.max_concurrent),
+            timeout: other.timeout.or(self.timeout),
+            retry: other.retry.or(self.retry),
+            cache_dir: other.cache_dir.or(self.cache_dir),
+            no_cache: other.no_cache.or(self.no_cache),
+            offline: other.offline.or(self.offline),
+            auth_cache_ttl: other.auth_cache_ttl.or(self.auth_cache_ttl),
+            output: other.output.or(self.output),
+        }
+    }
+}
+
+/// Parse a file mode written in octal, e.g. `644` or `0644`
+pub fn parse_mode(mode: &str) -> std::result::Result<u32, String> {
+    let digits = mode.strip_prefix("0o").unwrap_or(mode);
+    match u32::from_str_radix(digits, 8) {
+        Ok(parsed) if !digits.is_empty() && parsed <= 0o777 => Ok(parsed),
+        _ => Err(format!("'{}' is not an octal file mode such as 0644", mode)),
+    }
+}
+
+/// A mode given as an octal string (`"0644"`) or a TOML octal integer (`0o644`)
+fn deserialize_mode<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<u32>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Mode {
+        Number(u32),
+        Text(String),
+    }
+
+    let mode = match Mode::deserialize(deserializer)? {
+        Mode::Number(mode) => parse_mode(&format!("{:o}", mode)),
+        Mode::Text(mode) => parse_mode(&mode),
+    };
+    mode.map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+profile = "work"
+
+[defaults]
+max-concurrent = 20
+exclude = ["*.png"]
+progress = false
+
+[hosts."ghe.example.com"]
+token = "ghp_enterprise"
+api-url = "https://ghe.example.com/api/v3"
+
+[profiles.work]
+host = "ghe.example.com"
+progress = true
+
+[profiles.ci]
+quiet = true
+"#;
+
+    #[test]
+    fn test_profile_layering() {
+        let config = ConfigFile::parse(SAMPLE).unwrap();
+
+        let work = config.resolve(None).unwrap();
+        assert_eq!(work.host.as_deref(), Some("ghe.example.com"));
+        assert_eq!(work.progress, Some(true));
+        assert_eq!(work.max_concurrent, Some(20));
+        assert_eq!(work.exclude, Some(vec!["*.png".to_string()]));
+
+        let ci = config.resolve(Some("ci")).unwrap();
+        assert_eq!(ci.host, None);
+        assert_eq!(ci.progress, Some(false));
+        assert_eq!(ci.quiet, Some(true));
+
+        assert!(config.resolve(Some("missing")).is_err());
+        assert_eq!(config.host("ghe.example.com").unwrap().token.as_deref(), Some("ghp_enterprise"));
+    }
+
+    #[test]
+    fn test_credentials_roundtrip_is_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("credentials.toml");
+
+        let mut credentials = Credentials::load(&path).unwrap();
+        credentials.set("github.com", "gho_stored".to_string(), vec!["repo".to_string()]);
+        credentials.save(&path).unwrap();
+
+        let mut loaded = Credentials::load(&path).unwrap();
+        assert_eq!(loaded.get("github.com").unwrap().scopes, vec!["repo".to_string()]);
+        assert!(loaded.remove("github.com"));
+        assert!(!loaded.remove("github.com"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_default_permissions() {
+        let config = ConfigFile::parse("[defaults]\ndefault-permissions = \"0600\"\n").unwrap();
+        assert_eq!(config.defaults.default_permissions, Some(0o600));
+        let config = ConfigFile::parse("[defaults]\ndefault-permissions = 0o640\n").unwrap();
+        assert_eq!(config.defaults.default_permissions, Some(0o640));
+        assert!(ConfigFile::parse("[defaults]\ndefault-permissions = \"0999\"\n").is_err());
+        assert_eq!(parse_mode("644"), Ok(0o644));
+        assert!(parse_mode("1777").is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_rejected() {
+        assert!(ConfigFile::parse("[defaults]\nmax_concurrent = 3\n").is_err());
+        // Deleting files is only ever asked for on the command line
+        assert!(ConfigFile::parse("[defaults]\ndelete = true\n").is_err());
+    }
+}
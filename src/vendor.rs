@@ -0,0 +1,447 @@
+//! Declarative vendoring: `gcp.toml` names what to copy, `gcp.lock` pins it
+//!
+//! ```toml
+//! # gcp.toml
+//! [[source]]
+//! url = "https://github.com/owner/repo/tree/main/snippets"
+//! ref = "v1.2.0"                # optional; overrides the ref in the URL
+//! dest = "vendor/snippets"
+//! include = ["*.rs"]
+//! exclude = ["tests/**"]
+//! ```
+//!
+//! `gcp.lock` records, for every source, the commit its ref resolved to and the
+//! blob SHA of each file, so `gcp vendor` can reproduce the tree exactly and
+//! `gcp check` can verify it without network access.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::downloader::{diff, FileDownloader, FolderDownloader};
+use crate::error::{GcpError, Result};
+use crate::filesystem::{create_intermediate_dirs, git_blob_sha, is_within, matches_blob_sha, write_with_metadata, FileMetadata, PathFilter};
+use crate::github::{GitHubClient, GitHubUrl, UrlType};
+
+pub const MANIFEST_FILE: &str = "gcp.toml";
+pub const LOCK_FILE: &str = "gcp.lock";
+
+/// Format version written to `gcp.lock`
+const LOCK_VERSION: u32 = 1;
+
+/// The sources listed in `gcp.toml`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default, rename = "source")]
+    pub sources: Vec<ManifestSource>,
+}
+
+/// One `[[source]]` of the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestSource {
+    /// GitHub URL of a file or folder
+    pub url: String,
+    /// Branch, tag or commit to use instead of the URL's ref
+    #[serde(default, rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub ref_: Option<String>,
+    /// Destination relative to the manifest: a directory for folders, the file path for files
+    pub dest: PathBuf,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+/// The resolved state of every source, as written to `gcp.lock`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    #[serde(default, rename = "source")]
+    pub sources: Vec<LockedSource>,
+}
+
+/// A manifest source pinned to a commit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedSource {
+    #[serde(flatten)]
+    pub source: ManifestSource,
+    /// Full SHA of the commit the ref resolved to
+    pub commit: String,
+    #[serde(default, rename = "file")]
+    pub files: Vec<LockedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedFile {
+    /// Local path relative to the manifest, `/`-separated
+    pub path: String,
+    pub repo_path: String,
+    /// Git blob SHA of the content
+    pub sha: String,
+}
+
+/// How a locked file differs from the local tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Missing,
+    Modified,
+    /// The manifest source has no entry in the lock; `path` is its destination
+    Unlocked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckIssue {
+    pub path: String,
+    pub status: CheckStatus,
+}
+
+/// What `vendor_source` did
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VendorStats {
+    pub written: usize,
+    pub unchanged: usize,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| GcpError::FileIo { path: path.to_path_buf(), source: e })?;
+        let manifest: Self = toml::from_str(&content).map_err(|e| GcpError::Config {
+            message: format!("{}: {}", path.display(), e),
+        })?;
+        for source in &manifest.sources {
+            check_relative(path, "dest", &source.dest)?;
+        }
+        Ok(manifest)
+    }
+}
+
+/// Reject a path from the manifest or lock that could leave the project: an
+/// absolute path, or one with `..` components
+fn check_relative(file: &Path, field: &str, path: &Path) -> Result<()> {
+    use std::path::Component;
+
+    let contained = path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if contained && path.components().next().is_some() {
+        return Ok(());
+    }
+    Err(GcpError::Config {
+        message: format!("{}: {} '{}' must be a relative path inside the project", file.display(), field, path.display()),
+    })
+}
+
+impl Default for Lockfile {
+    fn default() -> Self {
+        Self { version: LOCK_VERSION, sources: Vec::new() }
+    }
+}
+
+impl Lockfile {
+    /// Read the lock, or `None` if there is none yet
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(GcpError::FileIo { path: path.to_path_buf(), source: e }),
+        };
+
+        let lock: Self = toml::from_str(&content).map_err(|e| GcpError::Config {
+            message: format!("{}: {}", path.display(), e),
+        })?;
+        if lock.version != LOCK_VERSION {
+            return Err(GcpError::Config {
+                message: format!("{}: unsupported lock version {}", path.display(), lock.version),
+            });
+        }
+        // A file may only be written where its source's `dest` puts it
+        for locked in &lock.sources {
+            check_relative(path, "dest", &locked.source.dest)?;
+            for file in &locked.files {
+                check_relative(path, "path", Path::new(&file.path))?;
+                if !Path::new(&file.path).starts_with(&locked.source.dest) {
+                    return Err(GcpError::Config {
+                        message: format!("{}: path '{}' is outside its source's dest '{}'", path.display(), file.path, locked.source.dest.display()),
+                    });
+                }
+            }
+        }
+        Ok(Some(lock))
+    }
+
+    /// Write the lock atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self).map_err(|e| GcpError::Config {
+            message: format!("Failed to serialize lock: {}", e),
+        })?;
+
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+        let io_err = |e: std::io::Error| GcpError::FileIo { path: path.to_path_buf(), source: e };
+
+        let mut temp = tempfile::NamedTempFile::new_in(dir).map_err(io_err)?;
+        writeln!(temp, "# Generated by gcp; do not edit by hand.").map_err(io_err)?;
+        temp.write_all(content.as_bytes()).map_err(io_err)?;
+        // The lock is meant to be committed, so it gets the usual file mode
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            temp.as_file()
+                .set_permissions(std::fs::Permissions::from_mode(0o644))
+                .map_err(io_err)?;
+        }
+        temp.persist(path).map_err(|e| io_err(e.error))?;
+
+        Ok(())
+    }
+
+    /// The locked entry for exactly this manifest source
+    pub fn find(&self, source: &ManifestSource) -> Option<&LockedSource> {
+        self.sources.iter().find(|locked| locked.source == *source)
+    }
+}
+
+/// Resolve a manifest source: pin its ref to a commit and list its files there
+pub async fn resolve(client: &std::sync::Arc<GitHubClient>, source: &ManifestSource) -> Result<LockedSource> {
+    let mut github_url = GitHubUrl::parse_with_host(&source.url, &client.config().github.host)?;
+    if source.ref_.is_some() {
+        github_url.ref_ = source.ref_.clone();
+    }
+    if github_url.ref_.is_none() {
+        github_url.ref_ = Some(client.get_repository_info(&github_url.owner, &github_url.repo).await?.default_branch);
+    }
+
+    let commit = client.resolve_commit_sha(&github_url.owner, &github_url.repo, github_url.ref_.as_deref()).await?;
+    debug!("{} resolved to {}", source.url, commit);
+
+    // List at the commit itself so the files match what was pinned
+    github_url.ref_ = Some(commit.clone());
+    let dest = source.dest.to_string_lossy().replace('\\', "/");
+    let dest = dest.trim_end_matches('/');
+
+    let files = if github_url.url_type == UrlType::File {
+        let repo_path = github_url.path.clone().unwrap_or_default();
+        let entry = client
+            .list_contents(&github_url.owner, &github_url.repo, &repo_path, Some(&commit))
+            .await?
+            .into_iter()
+            .find(|entry| entry.path == repo_path && entry.is_file())
+            .ok_or_else(|| GcpError::InvalidPath { path: repo_path.clone() })?;
+        vec![LockedFile { path: dest.to_string(), repo_path, sha: entry.sha }]
+    } else {
+        if github_url.url_type == UrlType::Repository {
+            github_url.url_type = UrlType::Folder;
+        }
+        let root = github_url.path.clone().unwrap_or_default();
+        let filter = PathFilter::new(&source.include, &source.exclude)?;
+        FolderDownloader::new(client.clone())
+            .list_recursive(&github_url)
+            .await?
+            .into_iter()
+            .filter(|entry| entry.is_file())
+            .filter_map(|entry| {
+                let relative = crate::downloader::folder::relative_path(root.trim_matches('/'), &entry.path).to_string();
+                diff::is_selected(&filter, &relative).then(|| LockedFile {
+                    path: format!("{}/{}", dest, relative),
+                    repo_path: entry.path,
+                    sha: entry.sha,
+                })
+            })
+            .collect()
+    };
+
+    Ok(LockedSource { source: source.clone(), commit, files })
+}
+
+/// Write every file of a locked source below `base`, verifying each blob SHA
+///
+/// Files that already have the locked content are left untouched.
+pub async fn vendor_source(client: &std::sync::Arc<GitHubClient>, locked: &LockedSource, base: &Path) -> Result<VendorStats> {
+    let github_url = GitHubUrl::parse_with_host(&locked.source.url, &client.config().github.host)?;
+    let downloader = FileDownloader::new(client.clone());
+    let mut stats = VendorStats::default();
+
+    let pending: Vec<&LockedFile> = locked.files.iter()
+        .filter(|file| !matches_blob_sha(&base.join(&file.path), &file.sha))
+        .collect();
+    stats.unchanged = locked.files.len() - pending.len();
+
+    // Modes and timestamps at the locked commit, fetched only when something is written
+    let source_url = GitHubUrl { ref_: Some(locked.commit.clone()), ..github_url.clone() };
+    let (tree, times) = if pending.is_empty() {
+        (None, Default::default())
+    } else {
+        let repo_paths: Vec<String> = pending.iter().map(|file| file.repo_path.clone()).collect();
+        (downloader.git_tree(&source_url).await, downloader.preserved_times(&source_url, &repo_paths).await?)
+    };
+
+    for file in pending {
+        let local_path = vendor_path(base, &file.path)?;
+        let file_url = GitHubUrl {
+            path: Some(file.repo_path.clone()),
+            ref_: Some(locked.commit.clone()),
+            url_type: UrlType::File,
+            ..github_url.clone()
+        };
+        let content = downloader.fetch_content(&file_url, Some(&file.sha)).await?;
+        let actual = git_blob_sha(&content);
+        if actual != file.sha {
+            return Err(GcpError::LockMismatch { path: file.path.clone(), expected: file.sha.clone(), actual });
+        }
+
+        let metadata = FileMetadata {
+            modified: times.get(&file.repo_path).copied(),
+            ..downloader.file_metadata(tree.as_ref(), &file.repo_path)
+        };
+
+        write_with_metadata(&local_path, &content, &metadata)?;
+        debug!("Wrote {}", local_path.display());
+        stats.written += 1;
+    }
+
+    info!("{}: {} written, {} unchanged", locked.source.url, stats.written, stats.unchanged);
+    Ok(stats)
+}
+
+/// Where a locked file is written below `base`, with its directory created
+///
+/// The directory is resolved, so a symlinked directory can't lead outside `base`.
+fn vendor_path(base: &Path, path: &str) -> Result<PathBuf> {
+    let local_path = base.join(path);
+    create_intermediate_dirs(&local_path)?;
+    let dir = local_path.parent().unwrap_or(base);
+    match is_within(base, dir) {
+        Ok(true) => Ok(local_path),
+        Ok(false) => Err(GcpError::InvalidPath { path: format!("{} is outside {}", local_path.display(), base.display()) }),
+        Err(e) => Err(GcpError::FileIo { path: dir.to_path_buf(), source: e }),
+    }
+}
+
+/// Compare the files below `base` with the lock
+///
+/// Manifest sources without a lock entry are reported as `Unlocked`.
+pub fn check(manifest: &Manifest, lock: &Lockfile, base: &Path) -> Vec<CheckIssue> {
+    let mut issues = Vec::new();
+    for source in &manifest.sources {
+        let Some(locked) = lock.find(source) else {
+            issues.push(CheckIssue { path: source.dest.display().to_string(), status: CheckStatus::Unlocked });
+            continue;
+        };
+
+        for file in &locked.files {
+            let local_path = base.join(&file.path);
+            let status = if !local_path.is_file() {
+                CheckStatus::Missing
+            } else if !matches_blob_sha(&local_path, &file.sha) {
+                CheckStatus::Modified
+            } else {
+                continue;
+            };
+            issues.push(CheckIssue { path: file.path.clone(), status });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const MANIFEST: &str = r#"
+[[source]]
+url = "https://github.com/o/r/tree/main/docs"
+ref = "v1"
+dest = "vendor/docs"
+exclude = ["*.png"]
+
+[[source]]
+url = "https://github.com/o/r/blob/main/README.md"
+dest = "vendor/README.md"
+"#;
+
+    #[test]
+    fn test_lock_roundtrip_and_check() {
+        let dir = tempdir().unwrap();
+        let manifest: Manifest = toml::from_str(MANIFEST).unwrap();
+        assert_eq!(manifest.sources[0].ref_.as_deref(), Some("v1"));
+
+        let lock = Lockfile {
+            version: LOCK_VERSION,
+            sources: vec![LockedSource {
+                source: manifest.sources[0].clone(),
+                commit: "abc".to_string(),
+                files: vec![
+                    LockedFile { path: "vendor/docs/a.md".to_string(), repo_path: "docs/a.md".to_string(), sha: git_blob_sha(b"hello\n") },
+                    LockedFile { path: "vendor/docs/b.md".to_string(), repo_path: "docs/b.md".to_string(), sha: git_blob_sha(b"b\n") },
+                    LockedFile { path: "vendor/docs/c.md".to_string(), repo_path: "docs/c.md".to_string(), sha: git_blob_sha(b"c\n") },
+                ],
+            }],
+        };
+        let lock_path = dir.path().join(LOCK_FILE);
+        lock.save(&lock_path).unwrap();
+        let lock = Lockfile::load(&lock_path).unwrap().unwrap();
+        assert_eq!(lock.sources[0].files.len(), 3);
+        assert!(lock.find(&manifest.sources[0]).is_some());
+
+        std::fs::create_dir_all(dir.path().join("vendor/docs")).unwrap();
+        std::fs::write(dir.path().join("vendor/docs/a.md"), b"hello\n").unwrap();
+        std::fs::write(dir.path().join("vendor/docs/b.md"), b"changed\n").unwrap();
+
+        assert_eq!(check(&manifest, &lock, dir.path()), [
+            CheckIssue { path: "vendor/docs/b.md".to_string(), status: CheckStatus::Modified },
+            CheckIssue { path: "vendor/docs/c.md".to_string(), status: CheckStatus::Missing },
+            CheckIssue { path: "vendor/README.md".to_string(), status: CheckStatus::Unlocked },
+        ]);
+    }
+
+    #[test]
+    fn test_lock_paths_stay_inside_the_project() {
+        let dir = tempdir().unwrap();
+        let lock_path = dir.path().join(LOCK_FILE);
+        let lock_with = |dest: &str, path: &str| format!(
+            "version = 1\n[[source]]\nurl = \"https://github.com/o/r/tree/main/docs\"\ndest = \"{}\"\ncommit = \"abc\"\n\
+             [[source.file]]\npath = \"{}\"\nrepo_path = \"docs/a.md\"\nsha = \"{}\"\n",
+            dest, path, git_blob_sha(b"ssh-ed25519 AAAA\n"),
+        );
+
+        std::fs::write(&lock_path, lock_with("vendor/docs", "vendor/docs/a.md")).unwrap();
+        assert!(Lockfile::load(&lock_path).unwrap().is_some());
+        for (dest, path) in [
+            ("vendor/docs", "vendor/docs/../../../.ssh/authorized_keys"),
+            ("vendor/docs", "/root/.ssh/authorized_keys"),
+            ("vendor/docs", "src/main.rs"),
+            ("../..", "../../.ssh/authorized_keys"),
+        ] {
+            std::fs::write(&lock_path, lock_with(dest, path)).unwrap();
+            assert!(matches!(Lockfile::load(&lock_path), Err(GcpError::Config { .. })), "{} accepted", path);
+        }
+
+        let manifest_path = dir.path().join(MANIFEST_FILE);
+        std::fs::write(&manifest_path, "[[source]]\nurl = \"https://github.com/o/r/tree/main/docs\"\ndest = \"../vendor\"\n").unwrap();
+        assert!(matches!(Manifest::load(&manifest_path), Err(GcpError::Config { .. })));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_vendor_path_refuses_symlinked_dirs() {
+        let dir = tempdir().unwrap();
+        let outside = tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("vendor")).unwrap();
+
+        assert!(vendor_path(dir.path(), "docs/a.md").is_ok());
+        assert!(matches!(vendor_path(dir.path(), "vendor/a.md"), Err(GcpError::InvalidPath { .. })));
+    }
+
+    #[test]
+    fn test_missing_lock_and_bad_version() {
+        let dir = tempdir().unwrap();
+        let lock_path = dir.path().join(LOCK_FILE);
+        assert!(Lockfile::load(&lock_path).unwrap().is_none());
+
+        std::fs::write(&lock_path, "version = 99\n").unwrap();
+        assert!(matches!(Lockfile::load(&lock_path), Err(GcpError::Config { .. })));
+    }
+}